@@ -0,0 +1,239 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{GetnfError, Result},
+    http, paths,
+    progress::Progress,
+    release,
+};
+
+/// one archive kept in the cache
+#[derive(Debug)]
+pub struct CachedArchive {
+    pub tag: String,
+    pub file: String,
+    pub size: u64,
+}
+
+/// downloaded release archives, laid out as `<cache dir>/<tag>/<file>`, plus
+/// read-only directories using the same layout
+#[derive(Debug)]
+pub struct Cache {
+    root: PathBuf,
+    archive_dirs: Vec<PathBuf>,
+}
+
+impl Cache {
+    pub fn open() -> Result<Self> {
+        Ok(Self {
+            root: paths::cache_dir()?,
+            archive_dirs: vec![],
+        })
+    }
+
+    /// also look for archives in `dirs`, after the cache itself
+    pub fn with_archive_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.archive_dirs = dirs;
+        self
+    }
+
+    fn roots(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.root).chain(&self.archive_dirs)
+    }
+
+    /// where the cache and archive dirs live, for error messages
+    pub fn searched(&self) -> String {
+        self.roots()
+            .map(|root| root.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// first local copy of `file` from release `tag`
+    pub fn find(&self, tag: &str, file: &str) -> Option<PathBuf> {
+        self.roots()
+            .map(|root| root.join(tag).join(file))
+            .find(|path| path.is_file())
+    }
+
+    /// keep a small release file (e.g. the checksum list) next to the archives
+    pub fn store(&self, tag: &str, file: &str, contents: &str) -> Result<()> {
+        let path = self.archive_path(tag, file);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| GetnfError::io(dir, e))?;
+        }
+        fs::write(&path, contents).map_err(|e| GetnfError::io(path, e))
+    }
+
+    pub fn archive_path(&self, tag: &str, file: &str) -> PathBuf {
+        self.root.join(tag).join(file)
+    }
+
+    /// download `url` to `dest`, going through a `.part` file so that an
+    /// interrupted download never looks like a cached archive
+    pub fn download(&self, url: &str, dest: &Path, font: &str, progress: &Progress) -> Result<u64> {
+        if let Some(dir) = dest.parent() {
+            fs::create_dir_all(dir).map_err(|e| GetnfError::io(dir, e))?;
+        }
+        let part = part_path(dest);
+        let written = http::download(url, &part, font, progress)?;
+        fs::rename(&part, dest).map_err(|e| GetnfError::io(dest, e))?;
+        Ok(written)
+    }
+
+    /// release tags present in the cache, newest first
+    pub fn tags(&self) -> Result<Vec<String>> {
+        tags_in([&self.root])
+    }
+
+    /// release tags with at least one archive in the cache or the archive
+    /// dirs, newest first
+    pub fn local_tags(&self) -> Result<Vec<String>> {
+        let mut tags = vec![];
+        for tag in tags_in(self.roots())? {
+            if !self.local_fonts(&tag)?.is_empty() {
+                tags.push(tag);
+            }
+        }
+        Ok(tags)
+    }
+
+    /// fonts with a local archive for release `tag`
+    pub fn local_fonts(&self, tag: &str) -> Result<Vec<String>> {
+        let mut fonts = vec![];
+        for root in self.roots() {
+            for path in read_dir(&root.join(tag))? {
+                let file = path.file_name().unwrap_or_default().to_string_lossy();
+                if let Some(font) = font_name(&file) {
+                    fonts.push(font.to_string());
+                }
+            }
+        }
+        fonts.sort();
+        fonts.dedup();
+        Ok(fonts)
+    }
+
+    /// every complete archive in the cache, newest release first
+    pub fn archives(&self) -> Result<Vec<CachedArchive>> {
+        let mut archives = vec![];
+        for tag in self.tags()? {
+            let mut files = read_dir(&self.root.join(&tag))?;
+            files.sort();
+            for path in files {
+                let file = path.file_name().unwrap_or_default().to_string_lossy();
+                let Ok(meta) = path.metadata() else { continue };
+                // skip partial downloads and checksum lists
+                if !meta.is_file() || font_name(&file).is_none() {
+                    continue;
+                }
+                archives.push(CachedArchive {
+                    tag: tag.clone(),
+                    file: file.to_string(),
+                    size: meta.len(),
+                });
+            }
+        }
+        Ok(archives)
+    }
+
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.archives()?.iter().map(|a| a.size).sum())
+    }
+
+    /// remove everything, returning the number of bytes freed
+    pub fn clean(&self) -> Result<u64> {
+        let mut freed = 0;
+        for tag in self.tags()? {
+            freed += self.remove_tag(&tag)?;
+        }
+        Ok(freed)
+    }
+
+    /// remove all but the `keep` newest releases, returning the number of
+    /// bytes freed
+    pub fn prune(&self, keep: usize) -> Result<u64> {
+        let mut freed = 0;
+        for tag in self.tags()?.iter().skip(keep) {
+            freed += self.remove_tag(tag)?;
+        }
+        Ok(freed)
+    }
+
+    fn remove_tag(&self, tag: &str) -> Result<u64> {
+        let dir = self.root.join(tag);
+        let size = read_dir(&dir)?
+            .iter()
+            .filter_map(|p| p.metadata().ok())
+            .map(|m| m.len())
+            .sum();
+        fs::remove_dir_all(&dir).map_err(|e| GetnfError::io(dir, e))?;
+        Ok(size)
+    }
+}
+
+/// font name of a release archive, `None` for other files
+pub fn font_name(file: &str) -> Option<&str> {
+    file.strip_suffix(".tar.xz")
+        .or_else(|| file.strip_suffix(".zip"))
+}
+
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    part.into()
+}
+
+fn tags_in<'a>(roots: impl IntoIterator<Item = &'a PathBuf>) -> Result<Vec<String>> {
+    let mut tags = vec![];
+    for root in roots {
+        for path in read_dir(root)? {
+            if path.is_dir() {
+                tags.push(
+                    path.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                );
+            }
+        }
+    }
+    tags.sort_by(|a, b| release::compare_tags(b, a));
+    tags.dedup();
+    Ok(tags)
+}
+
+/// entries of `dir`, nothing if it does not exist
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(GetnfError::io(dir, e)),
+    };
+    entries
+        .map(|entry| entry.map(|e| e.path()).map_err(|e| GetnfError::io(dir, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_tags_need_an_archive() {
+        let root = std::env::temp_dir().join(format!("getnf-cache-{}", std::process::id()));
+        fs::create_dir_all(root.join("v3.2.1")).unwrap();
+        fs::create_dir_all(root.join("v3.3.0")).unwrap();
+        fs::write(root.join("v3.2.1/Hack.tar.xz"), "").unwrap();
+        fs::write(root.join("v3.3.0/SHA-256.txt"), "").unwrap();
+        let cache = Cache {
+            root: root.clone(),
+            archive_dirs: vec![],
+        };
+        assert_eq!(cache.local_tags().unwrap(), ["v3.2.1"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+use std::{collections::HashMap, fs};
+
+use reqwest::StatusCode;
+
+use crate::{
+    cache::Cache,
+    endpoints::Endpoints,
+    error::{GetnfError, Result},
+    http,
+};
+
+/// name of the checksum asset published with every release
+const CHECKSUM_FILE: &str = "SHA-256.txt";
+
+/// sha256 of every asset of a release, keyed by file name
+#[derive(Debug, Default)]
+pub struct Checksums(HashMap<String, String>);
+
+impl Checksums {
+    /// fetch the checksum list published with release `tag`, keeping a copy
+    /// in the cache for offline use
+    pub fn fetch(tag: &str, endpoints: &Endpoints, cache: &Cache) -> Result<Self> {
+        let url = endpoints.asset(tag, CHECKSUM_FILE);
+        let text = match http::text(&url) {
+            Ok(text) => text,
+            // older releases publish no checksums
+            Err(GetnfError::HttpStatus { status, .. }) if status == StatusCode::NOT_FOUND => {
+                return Err(GetnfError::ChecksumMissing(format!("release {tag}")));
+            }
+            Err(e) => return Err(e),
+        };
+        cache.store(tag, CHECKSUM_FILE, &text)?;
+        Ok(Self::parse(&text))
+    }
+
+    /// the checksum list of release `tag` from the cache or archive dirs
+    pub fn local(tag: &str, cache: &Cache) -> Result<Self> {
+        let Some(path) = cache.find(tag, CHECKSUM_FILE) else {
+            return Err(GetnfError::NotAvailableOffline {
+                missing: vec![format!("{tag}/{CHECKSUM_FILE} (or use --skip-verify)")],
+                searched: cache.searched(),
+            });
+        };
+        let text = fs::read_to_string(&path).map_err(|e| GetnfError::io(path, e))?;
+        Ok(Self::parse(&text))
+    }
+
+    /// parse `sha256sum` style lines: `<hash>  <file>`
+    pub fn parse(text: &str) -> Self {
+        let sums = text
+            .lines()
+            .filter_map(|line| {
+                let (hash, file) = line.trim().split_once(char::is_whitespace)?;
+                let file = file.trim_start().trim_start_matches('*');
+                Some((file.to_string(), hash.to_ascii_lowercase()))
+            })
+            .collect();
+        Self(sums)
+    }
+
+    pub fn get(&self, file: &str) -> Option<&str> {
+        self.0.get(file).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_text_and_binary_mode_lines() {
+        let sums = Checksums::parse(
+            "ABC123  Foo.tar.xz\n\
+             def456 *Bar.zip\n\
+             \n\
+             0a0b0c\tBaz.zip\n",
+        );
+        assert_eq!(sums.get("Foo.tar.xz"), Some("abc123"));
+        assert_eq!(sums.get("Bar.zip"), Some("def456"));
+        assert_eq!(sums.get("Baz.zip"), Some("0a0b0c"));
+        assert_eq!(sums.get("*Bar.zip"), None);
+    }
+
+    #[test]
+    fn skips_lines_without_a_file() {
+        let sums = Checksums::parse("abc123\n   \nabc123  Foo.zip\n");
+        assert_eq!(sums.0.len(), 1);
+        assert_eq!(sums.get("Foo.zip"), Some("abc123"));
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::{
+    error::{GetnfError, Result},
+    filter::{OutlineFormat, Variant},
+    http::TlsRoots,
+    install::ArchiveFormat,
+    manifest::Scope,
+    paths,
+};
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// settable keys, besides `groups.<name>`
+const KEYS: &[&str] = &[
+    "scope",
+    "jobs",
+    "release",
+    "archive_format",
+    "format",
+    "variant",
+    "style",
+    "api_url",
+    "download_url",
+    "proxy",
+    "no_proxy",
+    "cacert",
+    "tls_roots",
+    "github_token",
+    "retries",
+    "connect_timeout",
+    "timeout",
+];
+
+/// persistent defaults, layered as system file < user file < `GETNF_*` env vars
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// `user` or `global`
+    pub scope: Option<Scope>,
+    /// fonts downloaded and unpacked at the same time
+    pub jobs: Option<u8>,
+    /// release tag to install and update to instead of the latest
+    pub release: Option<String>,
+    /// `auto`, `zip` or `tar.xz`
+    pub archive_format: Option<ArchiveFormat>,
+    /// `ttf`, `otf` or `both`, for families shipping both outline formats
+    pub format: Option<OutlineFormat>,
+    /// variants installed when `--variant` is not given
+    pub variant: Option<Vec<Variant>>,
+    /// styles installed when `--style` is not given
+    pub style: Option<Vec<String>>,
+    /// GitHub API base of the Nerd Fonts repository
+    pub api_url: Option<String>,
+    /// repository URL or asset URL template
+    pub download_url: Option<String>,
+    /// proxy used for every request, `http://`, `https://` or `socks5://`
+    pub proxy: Option<String>,
+    /// comma separated hosts that bypass the proxy, defaults to `NO_PROXY`
+    pub no_proxy: Option<String>,
+    /// PEM bundle of extra CA certificates to trust
+    pub cacert: Option<PathBuf>,
+    /// `system` or `bundled` root certificates, both when unset
+    pub tls_roots: Option<TlsRoots>,
+    /// GitHub token, takes precedence over `GITHUB_TOKEN` and `GH_TOKEN`
+    pub github_token: Option<String>,
+    /// extra attempts after a transient network failure
+    pub retries: Option<u32>,
+    /// seconds to wait for a connection
+    pub connect_timeout: Option<u64>,
+    /// seconds a single read may stall
+    pub timeout: Option<u64>,
+    /// named font lists, usable as `-f @name`
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+impl Config {
+    /// user config file
+    pub fn path() -> Result<PathBuf> {
+        Ok(paths::config_dir()?.join(CONFIG_FILE))
+    }
+
+    /// system-wide config file, read before the user one
+    pub fn system_path() -> Result<PathBuf> {
+        Ok(paths::system_config_dir()?.join(CONFIG_FILE))
+    }
+
+    /// the effective config: both files and the environment merged
+    pub fn load() -> Result<Self> {
+        let mut table = read_table(&Self::system_path()?)?;
+        merge(&mut table, read_table(&Self::path()?)?);
+        for key in KEYS {
+            let var = format!("GETNF_{}", key.to_ascii_uppercase());
+            if let Ok(raw) = env::var(&var) {
+                let value = parse_value(key, &raw).map_err(|reason| GetnfError::Config {
+                    path: var.into(),
+                    reason,
+                })?;
+                table.insert(key.to_string(), value);
+            }
+        }
+        from_table(table, Path::new("environment"))
+    }
+
+    /// flattened `key = value` pairs of every key that is set
+    pub fn entries(&self) -> Result<Vec<(String, Value)>> {
+        let table = Table::try_from(self).map_err(|e| GetnfError::InvalidInput(e.to_string()))?;
+        let mut entries = vec![];
+        for (key, value) in table {
+            match value {
+                Value::Table(groups) => {
+                    for (name, fonts) in groups {
+                        entries.push((format!("{key}.{name}"), fonts));
+                    }
+                }
+                value => entries.push((key, value)),
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<Value>> {
+        check_key(key)?;
+        Ok(self
+            .entries()?
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v))
+    }
+
+    /// expand `@group` names, leaving plain font names untouched
+    pub fn expand_groups(&self, fonts: Vec<String>) -> Result<Vec<String>> {
+        let mut expanded = vec![];
+        for font in fonts {
+            match font.strip_prefix('@') {
+                Some(group) => match self.groups.get(group) {
+                    Some(fonts) => expanded.extend(fonts.iter().cloned()),
+                    None => {
+                        return Err(GetnfError::InvalidInput(format!(
+                            "unknown font group `{group}`"
+                        )))
+                    }
+                },
+                None => expanded.push(font),
+            }
+        }
+        // keep the first mention of each font, in order
+        let mut seen = HashSet::new();
+        expanded.retain(|font| seen.insert(font.clone()));
+        Ok(expanded)
+    }
+}
+
+/// set `key` in the config file at `path`, keeping everything else as is;
+/// other keys are not checked, so that a broken file can be repaired
+pub fn set(path: &Path, key: &str, raw: &str) -> Result<()> {
+    check_key(key)?;
+    let value = parse_value(key, raw).map_err(GetnfError::InvalidInput)?;
+    let mut table = read_raw(path)?;
+    let mut single = Table::new();
+    match key.split_once('.') {
+        Some((group, name)) => {
+            let groups = table
+                .entry(group)
+                .or_insert_with(|| Value::Table(Table::new()));
+            if let Value::Table(groups) = groups {
+                groups.insert(name.into(), value.clone());
+            }
+            single.insert(
+                group.into(),
+                Value::Table(Table::from_iter([(name.into(), value)])),
+            );
+        }
+        None => {
+            table.insert(key.into(), value.clone());
+            single.insert(key.into(), value);
+        }
+    }
+    // refuse to write a value the loader would reject
+    from_table(single, path)?;
+    write_table(path, &table)
+}
+
+/// remove `key` from the config file at `path`; unknown keys too, so that a
+/// broken file can be repaired
+pub fn unset(path: &Path, key: &str) -> Result<()> {
+    let mut table = read_raw(path)?;
+    let removed = match key.split_once('.') {
+        Some((group, name)) => match table.get_mut(group) {
+            Some(Value::Table(groups)) => groups.remove(name).is_some(),
+            _ => false,
+        },
+        None => table.remove(key).is_some(),
+    };
+    if !removed {
+        return Err(GetnfError::InvalidInput(format!(
+            "`{key}` is not set in {}",
+            path.display()
+        )));
+    }
+    write_table(path, &table)
+}
+
+fn write_table(path: &Path, table: &Table) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| GetnfError::io(dir, e))?;
+    }
+    let text =
+        toml::to_string_pretty(table).map_err(|e| GetnfError::InvalidInput(e.to_string()))?;
+    fs::write(path, text).map_err(|e| GetnfError::io(path, e))
+}
+
+fn check_key(key: &str) -> Result<()> {
+    let known = KEYS.contains(&key) || key.strip_prefix("groups.").is_some_and(|g| !g.is_empty());
+    if known {
+        return Ok(());
+    }
+    Err(GetnfError::InvalidInput(format!(
+        "unknown config key `{key}`, expected one of {}, groups.<name>",
+        KEYS.join(", ")
+    )))
+}
+
+/// turn a command line or env var value into the toml value for `key`
+fn parse_value(key: &str, raw: &str) -> std::result::Result<Value, String> {
+    match key {
+        "jobs" => match raw.trim().parse::<u8>() {
+            Ok(jobs) if jobs > 0 => Ok(Value::Integer(jobs.into())),
+            _ => Err(format!(
+                "jobs must be a number between 1 and 255, got `{raw}`"
+            )),
+        },
+        "retries" | "connect_timeout" | "timeout" => match raw.trim().parse::<u32>() {
+            Ok(n) => Ok(Value::Integer(n.into())),
+            _ => Err(format!("{key} must be a non-negative number, got `{raw}`")),
+        },
+        "scope" => match raw.trim() {
+            scope @ ("user" | "global") => Ok(Value::String(scope.into())),
+            _ => Err(format!("scope must be `user` or `global`, got `{raw}`")),
+        },
+        "archive_format" => match raw.trim() {
+            format @ ("auto" | "zip" | "tar.xz") => Ok(Value::String(format.into())),
+            _ => Err(format!(
+                "archive_format must be `auto`, `zip` or `tar.xz`, got `{raw}`"
+            )),
+        },
+        "format" => match raw.trim() {
+            format @ ("ttf" | "otf" | "both") => Ok(Value::String(format.into())),
+            _ => Err(format!(
+                "format must be `ttf`, `otf` or `both`, got `{raw}`"
+            )),
+        },
+        "variant" => list(raw)
+            .map(|variant| match variant {
+                "default" | "mono" | "propo" => Ok(Value::String(variant.into())),
+                _ => Err(format!(
+                    "variant must be `default`, `mono` or `propo`, got `{variant}`"
+                )),
+            })
+            .collect::<std::result::Result<_, _>>()
+            .map(Value::Array),
+        "style" => Ok(Value::Array(
+            list(raw).map(|style| Value::String(style.into())).collect(),
+        )),
+        "tls_roots" => match raw.trim() {
+            roots @ ("system" | "bundled") => Ok(Value::String(roots.into())),
+            _ => Err(format!(
+                "tls_roots must be `system` or `bundled`, got `{raw}`"
+            )),
+        },
+        _ if key.starts_with("groups.") => Ok(Value::Array(
+            list(raw).map(|f| Value::String(f.into())).collect(),
+        )),
+        _ => Ok(Value::String(raw.into())),
+    }
+}
+
+/// items of a comma separated value
+fn list(raw: &str) -> impl Iterator<Item = &str> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+fn read_table(path: &Path) -> Result<Table> {
+    let table = read_raw(path)?;
+    // report unknown keys against the file that holds them
+    from_table(table.clone(), path)?;
+    Ok(table)
+}
+
+/// the config file at `path` as plain toml, empty if there is none
+fn read_raw(path: &Path) -> Result<Table> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Table::new()),
+        Err(e) => return Err(GetnfError::io(path, e)),
+    };
+    text.parse::<Table>().map_err(|e| GetnfError::Config {
+        path: path.into(),
+        reason: e.message().into(),
+    })
+}
+
+fn from_table(table: Table, path: &Path) -> Result<Config> {
+    let config = Value::Table(table)
+        .try_into::<Config>()
+        .map_err(|e| GetnfError::Config {
+            path: path.into(),
+            reason: e.message().into(),
+        })?;
+    if config.jobs == Some(0) {
+        return Err(GetnfError::Config {
+            path: path.into(),
+            reason: "jobs must be at least 1".into(),
+        });
+    }
+    Ok(config)
+}
+
+/// `upper` wins over `base`; group tables are merged name by name
+fn merge(base: &mut Table, upper: Table) {
+    for (key, value) in upper {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(upper)) => base.extend(upper),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(text: &str) -> Table {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_validates_values() {
+        assert_eq!(parse_value("jobs", " 4 "), Ok(Value::Integer(4)));
+        assert!(parse_value("jobs", "0").is_err());
+        assert!(parse_value("jobs", "many").is_err());
+        assert!(parse_value("format", "woff").is_err());
+        assert_eq!(
+            parse_value("variant", "mono, propo"),
+            Ok(Value::Array(vec!["mono".into(), "propo".into()]))
+        );
+        assert!(parse_value("variant", "mono,wide").is_err());
+        assert_eq!(
+            parse_value("groups.coding", "Hack, ,FiraCode"),
+            Ok(Value::Array(vec!["Hack".into(), "FiraCode".into()]))
+        );
+    }
+
+    #[test]
+    fn upper_layer_wins_and_groups_merge_by_name() {
+        let mut base = table(
+            "jobs = 2\nscope = \"global\"\n[groups]\ncoding = [\"Hack\"]\nterm = [\"Iosevka\"]\n",
+        );
+        merge(
+            &mut base,
+            table("jobs = 8\n[groups]\ncoding = [\"FiraCode\"]\n"),
+        );
+        let config = from_table(base, Path::new("test")).unwrap();
+        assert_eq!(config.jobs, Some(8));
+        assert_eq!(config.scope, Some(Scope::Global));
+        assert_eq!(config.groups["coding"], ["FiraCode"]);
+        assert_eq!(config.groups["term"], ["Iosevka"]);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_zero_jobs() {
+        assert!(from_table(table("jobz = 3"), Path::new("test")).is_err());
+        assert!(from_table(table("jobs = 0"), Path::new("test")).is_err());
+    }
+
+    #[test]
+    fn expands_groups_once_per_font() {
+        let config = from_table(
+            table("[groups]\ncoding = [\"Hack\", \"FiraCode\"]\n"),
+            Path::new("test"),
+        )
+        .unwrap();
+        let fonts = ["Hack", "@coding", "Iosevka"].map(String::from).to_vec();
+        assert_eq!(
+            config.expand_groups(fonts).unwrap(),
+            ["Hack", "FiraCode", "Iosevka"]
+        );
+        assert!(config.expand_groups(vec!["@nope".into()]).is_err());
+    }
+
+    #[test]
+    fn set_and_unset_repair_unknown_keys() {
+        let dir = std::env::temp_dir().join(format!("getnf-config-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "jobz = 3\n").unwrap();
+        set(&path, "jobs", "2").unwrap();
+        assert!(set(&path, "jobs", "0").is_err());
+        unset(&path, "jobz").unwrap();
+        assert!(unset(&path, "jobz").is_err());
+        assert_eq!(read_table(&path).unwrap(), table("jobs = 2"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
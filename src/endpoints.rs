@@ -0,0 +1,99 @@
+use crate::cache;
+
+/// GitHub API of the Nerd Fonts repository
+pub const NERD_FONTS_API: &str = "https://api.github.com/repos/ryanoasis/nerd-fonts";
+/// where release assets are downloaded from
+pub const NERD_FONTS_REPO: &str = "https://github.com/ryanoasis/nerd-fonts";
+
+/// where getnf talks to, overridable for mirrors and enterprise hosts
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    api: String,
+    download: String,
+}
+
+impl Endpoints {
+    /// `api` is the repository's API base, `download` either a repository URL
+    /// (GitHub layout) or a template using `{tag}`, `{file}` and `{font}`
+    pub fn new(api: Option<String>, download: Option<String>) -> Self {
+        let api = api.unwrap_or_else(|| NERD_FONTS_API.into());
+        let download = download.unwrap_or_else(|| NERD_FONTS_REPO.into());
+        let download = if download.contains('{') {
+            download
+        } else {
+            format!(
+                "{}/releases/download/{{tag}}/{{file}}",
+                download.trim_end_matches('/')
+            )
+        };
+        Self {
+            api: api.trim_end_matches('/').into(),
+            download,
+        }
+    }
+
+    /// API url for `path`, e.g. `/releases/latest`
+    pub fn api(&self, path: &str) -> String {
+        format!("{}{path}", self.api)
+    }
+
+    /// download url of the release asset `file`
+    pub fn asset(&self, tag: &str, file: &str) -> String {
+        let font = cache::font_name(file).unwrap_or(file);
+        self.download
+            .replace("{tag}", tag)
+            .replace("{file}", file)
+            .replace("{font}", font)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_github() {
+        let endpoints = Endpoints::new(None, None);
+        assert_eq!(
+            endpoints.api("/releases/latest"),
+            format!("{NERD_FONTS_API}/releases/latest")
+        );
+        assert_eq!(
+            endpoints.asset("v3.2.1", "Hack.tar.xz"),
+            format!("{NERD_FONTS_REPO}/releases/download/v3.2.1/Hack.tar.xz")
+        );
+    }
+
+    #[test]
+    fn repository_urls_get_the_github_layout() {
+        let endpoints = Endpoints::new(
+            Some("https://ghe.example/api/v3/repos/fonts/nerd-fonts/".into()),
+            Some("https://ghe.example/fonts/nerd-fonts/".into()),
+        );
+        assert_eq!(
+            endpoints.api("/releases"),
+            "https://ghe.example/api/v3/repos/fonts/nerd-fonts/releases"
+        );
+        assert_eq!(
+            endpoints.asset("v3.2.1", "Hack.zip"),
+            "https://ghe.example/fonts/nerd-fonts/releases/download/v3.2.1/Hack.zip"
+        );
+    }
+
+    #[test]
+    fn templates_fill_tag_file_and_font() {
+        let endpoints = Endpoints::new(
+            None,
+            Some("https://mirror.example/{tag}/{font}/{file}".into()),
+        );
+        assert_eq!(
+            endpoints.asset("v3.2.1", "Hack.tar.xz"),
+            "https://mirror.example/v3.2.1/Hack/Hack.tar.xz"
+        );
+        // files that are not font archives stand in for the font too
+        assert_eq!(
+            endpoints.asset("v3.2.1", "SHA-256.txt"),
+            "https://mirror.example/v3.2.1/SHA-256.txt/SHA-256.txt"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::{io, path::PathBuf};
+
+use reqwest::StatusCode;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, GetnfError>;
+
+/// every way a getnf command can fail
+#[derive(Debug, Error)]
+pub enum GetnfError {
+    /// the request never got a response (dns, tls, connection reset, ...)
+    #[error("network error: {source}")]
+    Network {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    /// the connection broke while reading a response body
+    #[error("download of {url} interrupted: {source}")]
+    Interrupted {
+        url: String,
+        #[source]
+        source: io::Error,
+    },
+    /// the GitHub API refused the request because the rate limit is exhausted
+    #[error(
+        "GitHub API rate limited{}{}",
+        resets_at.as_ref().map(|t| format!(", resets at {t}")).unwrap_or_default(),
+        if *authenticated { "" } else { " (set GITHUB_TOKEN or GH_TOKEN to raise the limit)" }
+    )]
+    RateLimited {
+        /// local `HH:MM` at which requests are allowed again
+        resets_at: Option<String>,
+        authenticated: bool,
+    },
+    /// the server answered with a non-success status
+    #[error("{url} returned HTTP {status}")]
+    HttpStatus { url: String, status: StatusCode },
+    /// the response body did not have the expected shape
+    #[error("unexpected response from {url}: {reason}")]
+    InvalidResponse { url: String, reason: String },
+    /// the font is neither known remotely nor installed
+    #[error("font not found: {0}")]
+    FontNotFound(String),
+    /// the repository has no release with that tag
+    #[error("release not found: {0}")]
+    ReleaseNotFound(String),
+    #[error("permission denied: {} (try running with the right privileges or without --global)", path.display())]
+    PermissionDenied { path: PathBuf },
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// the install manifest exists but cannot be read or written
+    #[error("corrupt manifest {}: {source}", path.display())]
+    Manifest {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// the downloaded archive does not match the published checksum
+    #[error("checksum mismatch for {file}: expected {expected}, got {actual} (use --skip-verify to install anyway)")]
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+    /// the release does not publish a checksum for the archive
+    #[error("no published checksum for {0} (use --skip-verify to install anyway)")]
+    ChecksumMissing(String),
+    /// `--offline` was given but the files needed are not available locally
+    #[error("not available offline: {} (searched {searched})", missing.join(", "))]
+    NotAvailableOffline {
+        missing: Vec<String>,
+        searched: String,
+    },
+    #[error("failed to unpack {font}: {source}")]
+    Archive {
+        font: String,
+        #[source]
+        source: arkiv::Error,
+    },
+    /// some fonts of a batch failed, the others went through
+    #[error("{} of {total} font(s) failed: {}", failed.len(), failed.iter().map(|(f, _)| f.as_str()).collect::<Vec<_>>().join(", "))]
+    Failed {
+        total: usize,
+        failed: Vec<(String, GetnfError)>,
+    },
+    #[error("invalid config {}: {reason}", path.display())]
+    Config { path: PathBuf, reason: String },
+    #[error("environment variable {0} is not set")]
+    MissingEnv(&'static str),
+    #[error("unsupported platform: {0}")]
+    UnsupportedPlatform(&'static str),
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("interactive selection failed: {0}")]
+    Prompt(#[from] dialoguer::Error),
+}
+
+impl GetnfError {
+    /// wrap an io error, singling out permission problems
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        let path = path.into();
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied { path },
+            _ => Self::Io { path, source },
+        }
+    }
+
+    /// process exit code, stable so that scripts can branch on it
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::InvalidInput(_) | Self::Prompt(_) => 2,
+            Self::FontNotFound(_) | Self::ReleaseNotFound(_) => 3,
+            Self::Network { .. } | Self::Interrupted { .. } => 4,
+            Self::RateLimited { .. } => 5,
+            Self::HttpStatus { .. } => 6,
+            Self::InvalidResponse { .. } => 7,
+            Self::PermissionDenied { .. } => 8,
+            Self::Io { .. } => 9,
+            Self::Archive { .. } => 10,
+            Self::MissingEnv(_) | Self::UnsupportedPlatform(_) => 11,
+            Self::Manifest { .. } => 12,
+            Self::ChecksumMismatch { .. } | Self::ChecksumMissing(_) => 13,
+            Self::NotAvailableOffline { .. } => 14,
+            Self::Config { .. } => 15,
+            // the first failure decides, so a single failing font keeps its code
+            Self::Failed { failed, .. } => failed.first().map_or(1, |(_, e)| e.exit_code()),
+        }
+    }
+}
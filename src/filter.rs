@@ -0,0 +1,225 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// spacing variant of a Nerd Font, from the file name suffix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    /// `FooNerdFont-Regular.ttf`, double width icons
+    Default,
+    /// `FooNerdFontMono-Regular.ttf`, single width icons
+    Mono,
+    /// `FooNerdFontPropo-Regular.ttf`, proportional
+    Propo,
+}
+
+/// outline format to install when a family ships both `.ttf` and `.otf`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutlineFormat {
+    /// TrueType, falling back to OpenType for families without it
+    #[default]
+    Ttf,
+    /// OpenType, falling back to TrueType for families without it
+    Otf,
+    /// both, which font pickers may list twice
+    Both,
+}
+
+impl OutlineFormat {
+    /// files of `files` to skip: those in the other format of families that
+    /// also ship the preferred one
+    pub fn duplicates(self, files: &[PathBuf]) -> Vec<PathBuf> {
+        let (preferred, other) = match self {
+            Self::Ttf => ("ttf", "otf"),
+            Self::Otf => ("otf", "ttf"),
+            Self::Both => return vec![],
+        };
+        let families = files
+            .iter()
+            .filter(|f| has_extension(f, preferred))
+            .filter_map(|f| family(f))
+            .collect::<HashSet<_>>();
+        files
+            .iter()
+            .filter(|f| has_extension(f, other))
+            .filter(|f| family(f).is_some_and(|family| families.contains(&family)))
+            .cloned()
+            .collect()
+    }
+}
+
+/// which font files of an archive to install; other files (licenses,
+/// readmes) are always kept
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FontFilter {
+    /// every variant when empty
+    pub variants: Vec<Variant>,
+    /// e.g. `Regular`, `BoldItalic`; every style when empty
+    pub styles: Vec<String>,
+}
+
+impl FontFilter {
+    /// whether the archive file at `path` should be installed
+    pub fn keeps(&self, path: &Path) -> bool {
+        let Some(font) = FontFile::parse(path) else {
+            return true;
+        };
+        let variant =
+            self.variants.is_empty() || font.variant.is_none_or(|v| self.variants.contains(&v));
+        let style = self.styles.is_empty()
+            || font
+                .style
+                .is_none_or(|s| self.styles.iter().any(|f| f.eq_ignore_ascii_case(s)));
+        variant && style
+    }
+
+    /// `--variant` and `--style` flags reproducing this filter
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+        if !self.variants.is_empty() {
+            let variants = self
+                .variants
+                .iter()
+                .map(|v| format!("{v:?}").to_lowercase())
+                .collect::<Vec<_>>();
+            parts.push(format!("--variant {}", variants.join(",")));
+        }
+        if !self.styles.is_empty() {
+            parts.push(format!("--style {}", self.styles.join(",")));
+        }
+        parts.join(" ")
+    }
+}
+
+/// whether `path` is a font file, as opposed to a license or readme
+pub fn is_font(path: &Path) -> bool {
+    FontFile::parse(path).is_some()
+}
+
+fn has_extension(path: &Path, ext: &str) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
+
+/// directory and file name without style and extension, e.g.
+/// `Foo/FooNerdFontMono` for `Foo/FooNerdFontMono-Bold.otf`
+fn family(path: &Path) -> Option<PathBuf> {
+    let stem = path.file_stem()?.to_str()?;
+    let family = stem.rsplit_once('-').map_or(stem, |(family, _)| family);
+    Some(path.with_file_name(family))
+}
+
+/// what the name of a font file tells, `None` parts when it does not follow
+/// the `<Family>NerdFont[Mono|Propo]-<Style>` scheme
+struct FontFile<'a> {
+    variant: Option<Variant>,
+    style: Option<&'a str>,
+}
+
+impl<'a> FontFile<'a> {
+    /// `None` for anything but `.ttf` and `.otf` files
+    fn parse(path: &'a Path) -> Option<Self> {
+        if !has_extension(path, "ttf") && !has_extension(path, "otf") {
+            return None;
+        }
+        let stem = path.file_stem()?.to_str()?;
+        let (family, style) = match stem.rsplit_once('-') {
+            Some((family, style)) => (family, Some(style)),
+            None => (stem, None),
+        };
+        let variant = family
+            .rfind("NerdFont")
+            .map(|i| &family[i + "NerdFont".len()..])
+            .and_then(|suffix| match suffix {
+                "" => Some(Variant::Default),
+                "Mono" => Some(Variant::Mono),
+                "Propo" => Some(Variant::Propo),
+                _ => None,
+            });
+        Some(Self {
+            variant,
+            style: style.filter(|_| variant.is_some()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str) -> Option<(Option<Variant>, Option<&str>)> {
+        FontFile::parse(Path::new(name)).map(|f| (f.variant, f.style))
+    }
+
+    #[test]
+    fn parses_nerd_font_names() {
+        assert_eq!(
+            parse("FooNerdFontPropo-BoldItalic.otf"),
+            Some((Some(Variant::Propo), Some("BoldItalic")))
+        );
+        assert_eq!(
+            parse("Foo/FooNerdFontMono-Regular.TTF"),
+            Some((Some(Variant::Mono), Some("Regular")))
+        );
+        assert_eq!(
+            parse("FooNerdFont-Bold.ttf"),
+            Some((Some(Variant::Default), Some("Bold")))
+        );
+        assert_eq!(
+            parse("FooNerdFont.ttf"),
+            Some((Some(Variant::Default), None))
+        );
+    }
+
+    #[test]
+    fn other_names_are_kept_whole() {
+        assert_eq!(parse("Foo-Regular.ttf"), Some((None, None)));
+        assert_eq!(parse("FooNerdFontWide-Regular.ttf"), Some((None, None)));
+        assert_eq!(parse("LICENSE"), None);
+        assert_eq!(parse("readme.md"), None);
+    }
+
+    #[test]
+    fn filter_keeps_matching_and_unknown_files() {
+        let filter = FontFilter {
+            variants: vec![Variant::Mono],
+            styles: vec!["bold".into()],
+        };
+        assert!(filter.keeps(Path::new("FooNerdFontMono-Bold.ttf")));
+        assert!(!filter.keeps(Path::new("FooNerdFontMono-Regular.ttf")));
+        assert!(!filter.keeps(Path::new("FooNerdFontPropo-Bold.ttf")));
+        assert!(filter.keeps(Path::new("LICENSE")));
+        assert!(filter.keeps(Path::new("Foo-Regular.ttf")));
+    }
+
+    #[test]
+    fn duplicates_only_in_families_shipping_both_formats() {
+        let files = [
+            "Foo/FooNerdFont-Regular.ttf",
+            "Foo/FooNerdFont-Regular.otf",
+            "Foo/FooNerdFont-Bold.otf",
+            "Foo/BarNerdFont-Regular.otf",
+            "Foo/LICENSE",
+        ]
+        .map(PathBuf::from);
+        assert_eq!(
+            OutlineFormat::Ttf.duplicates(&files),
+            [
+                PathBuf::from("Foo/FooNerdFont-Regular.otf"),
+                PathBuf::from("Foo/FooNerdFont-Bold.otf"),
+            ]
+        );
+        assert_eq!(
+            OutlineFormat::Otf.duplicates(&files),
+            [PathBuf::from("Foo/FooNerdFont-Regular.ttf")]
+        );
+        assert!(OutlineFormat::Both.duplicates(&files).is_empty());
+    }
+}
@@ -0,0 +1,30 @@
+use std::{
+    env, io,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// rebuild fontconfig's cache for `dir` so that running applications see
+/// fonts added or removed there; only Linux keeps a cache they do not refresh
+/// themselves. Failures are reported, never fatal: the fonts are in place
+pub fn refresh(dir: &Path) {
+    if env::consts::OS != "linux" || !dir.is_dir() {
+        return;
+    }
+    let status = Command::new("fc-cache")
+        .arg("-f")
+        .arg(dir)
+        .stdout(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!(
+            "fc-cache -f {} failed ({status}), run it again for applications to see the change",
+            dir.display()
+        ),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => eprintln!(
+            "fc-cache not found: install fontconfig, or restart applications to see the change"
+        ),
+        Err(e) => eprintln!("cannot run fc-cache: {e}"),
+    }
+}
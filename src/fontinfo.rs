@@ -0,0 +1,108 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ttf_parser::{name::Table, name_id, Language, RawFace, Tag};
+
+use crate::error::{GetnfError, Result};
+
+/// names a font file declares in its OpenType `name` table, i.e. what
+/// applications list it as
+#[derive(Debug, Clone)]
+pub struct FontInfo {
+    pub family: String,
+    pub subfamily: String,
+    pub postscript: Option<String>,
+}
+
+impl FontInfo {
+    /// the names of the font at `path`, `None` if it is not a font getnf can
+    /// read
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let data = fs::read(path).map_err(|e| GetnfError::io(path, e))?;
+        Ok(Self::parse(&data))
+    }
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let face = RawFace::parse(data, 0).ok()?;
+        let table = Table::parse(face.table(Tag::from_bytes(b"name"))?)?;
+        let name = |id| {
+            let mut names = table
+                .names
+                .into_iter()
+                .filter(|n| n.name_id == id && n.is_unicode());
+            let first = names.clone().next();
+            names
+                .find(|n| n.language() == Language::English_UnitedStates)
+                .or(first)
+                .and_then(|n| n.to_string())
+        };
+        // the typographic names group weights the way font pickers do
+        Some(Self {
+            family: name(name_id::TYPOGRAPHIC_FAMILY).or_else(|| name(name_id::FAMILY))?,
+            subfamily: name(name_id::TYPOGRAPHIC_SUBFAMILY)
+                .or_else(|| name(name_id::SUBFAMILY))
+                .unwrap_or_else(|| "Regular".into()),
+            postscript: name(name_id::POST_SCRIPT_NAME),
+        })
+    }
+
+    /// Nerd Fonts variant: `NFM` (mono), `NFP` (proportional) or `NF`, `None`
+    /// for fonts that are not patched
+    pub fn variant(&self) -> Option<&'static str> {
+        let family = self.family.as_str();
+        if family.ends_with("Nerd Font Mono") || family.ends_with(" NFM") {
+            Some("NFM")
+        } else if family.ends_with("Nerd Font Propo") || family.ends_with(" NFP") {
+            Some("NFP")
+        } else if family.ends_with("Nerd Font") || family.ends_with(" NF") {
+            Some("NF")
+        } else {
+            None
+        }
+    }
+}
+
+/// fonts of one family, as a font picker groups them
+#[derive(Debug)]
+pub struct Family {
+    pub name: String,
+    pub variant: Option<&'static str>,
+    /// each face with its file, relative to the font dir
+    pub faces: Vec<(FontInfo, PathBuf)>,
+}
+
+/// group `files` (relative to `dir`) by family; the second list holds the
+/// files that could not be read or parsed
+pub fn families(dir: &Path, files: &[PathBuf]) -> (Vec<Family>, Vec<PathBuf>) {
+    let mut families = BTreeMap::<String, Family>::new();
+    let mut unreadable = vec![];
+    for file in files {
+        let Ok(Some(info)) = FontInfo::read(&dir.join(file)) else {
+            unreadable.push(file.clone());
+            continue;
+        };
+        families
+            .entry(info.family.clone())
+            .or_insert_with(|| Family {
+                name: info.family.clone(),
+                variant: info.variant(),
+                faces: vec![],
+            })
+            .faces
+            .push((info, file.clone()));
+    }
+    (families.into_values().collect(), unreadable)
+}
+
+impl Family {
+    /// name followed by the variant, e.g. `Hack Nerd Font Mono (NFM)`
+    pub fn label(&self) -> String {
+        match self.variant {
+            Some(variant) => format!("{} ({variant})", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
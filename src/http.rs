@@ -0,0 +1,559 @@
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, Utc};
+use indicatif::ProgressBar;
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    header::{HeaderMap, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE, USER_AGENT},
+    Certificate, NoProxy, Proxy, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::{GetnfError, Result},
+    progress::Progress,
+};
+
+pub const DEFAULT_RETRIES: u32 = 3;
+pub const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+pub const DEFAULT_TIMEOUT: u64 = 30;
+
+/// root certificates trusted for https, besides `--cacert`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsRoots {
+    /// the platform trust store
+    System,
+    /// the Mozilla roots built into getnf
+    Bundled,
+}
+
+/// how requests are made, from the config and the command line
+#[derive(Debug)]
+pub struct HttpOptions {
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy for every
+    /// request; when unset the usual `HTTPS_PROXY`/`ALL_PROXY` vars apply
+    pub proxy: Option<String>,
+    /// comma separated hosts that bypass `proxy`, defaults to `NO_PROXY`
+    pub no_proxy: Option<String>,
+    /// extra PEM certificates to trust, e.g. a corporate CA
+    pub cacert: Option<PathBuf>,
+    /// both the system and the bundled roots when unset
+    pub tls_roots: Option<TlsRoots>,
+    /// configured token, sent to the API whatever its host
+    pub token: Option<String>,
+    /// `GITHUB_TOKEN`/`GH_TOKEN`, only sent to api.github.com since it was
+    /// not meant for a mirror
+    pub env_token: Option<String>,
+    /// extra attempts after a transient failure
+    pub retries: u32,
+    pub connect_timeout: Duration,
+    /// how long a single read may stall
+    pub timeout: Duration,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            no_proxy: None,
+            cacert: None,
+            tls_roots: None,
+            token: None,
+            env_token: None,
+            retries: DEFAULT_RETRIES,
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+struct Http {
+    client: Client,
+    token: Option<String>,
+    env_token: Option<String>,
+    retries: u32,
+}
+
+static HTTP: OnceLock<Http> = OnceLock::new();
+
+/// build the client shared by every request; must run before the first one
+pub fn init(opts: HttpOptions) -> Result<()> {
+    let mut builder = Client::builder()
+        .connect_timeout(opts.connect_timeout)
+        .timeout(opts.timeout);
+    let no_proxy = match &opts.no_proxy {
+        Some(hosts) => NoProxy::from_string(hosts),
+        None => NoProxy::from_env(),
+    };
+    let invalid_proxy =
+        |proxy: &str, e| GetnfError::InvalidInput(format!("invalid proxy `{proxy}`: {e}"));
+    if let Some(proxy) = &opts.proxy {
+        let proxy = Proxy::all(proxy)
+            .map_err(|e| invalid_proxy(proxy, e))?
+            .no_proxy(no_proxy);
+        builder = builder.proxy(proxy);
+    } else if opts.no_proxy.is_some() {
+        // reqwest only applies `NO_PROXY` to the proxies it reads from the
+        // environment itself, so set them up here to apply ours
+        for (https, vars) in [
+            (
+                true,
+                ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"],
+            ),
+            (
+                false,
+                ["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"],
+            ),
+        ] {
+            let Some(proxy) = vars
+                .iter()
+                .find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()))
+            else {
+                continue;
+            };
+            let proxy = match https {
+                true => Proxy::https(&proxy),
+                false => Proxy::http(&proxy),
+            }
+            .map_err(|e| invalid_proxy(&proxy, e))?
+            .no_proxy(no_proxy.clone());
+            builder = builder.proxy(proxy);
+        }
+    }
+    if let Some(path) = &opts.cacert {
+        let pem = fs::read(path).map_err(|e| GetnfError::io(path, e))?;
+        let invalid = |reason: String| {
+            GetnfError::InvalidInput(format!("invalid CA bundle {}: {reason}", path.display()))
+        };
+        let certs = Certificate::from_pem_bundle(&pem).map_err(|e| invalid(e.to_string()))?;
+        if certs.is_empty() {
+            return Err(invalid("no PEM certificate found".into()));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    match opts.tls_roots {
+        Some(TlsRoots::System) => builder = builder.tls_built_in_webpki_certs(false),
+        Some(TlsRoots::Bundled) => builder = builder.tls_built_in_native_certs(false),
+        None => {}
+    }
+    let client = builder
+        .build()
+        .map_err(|e| GetnfError::InvalidInput(format!("cannot set up http client: {e}")))?;
+    HTTP.set(Http {
+        client,
+        token: opts.token,
+        env_token: opts.env_token,
+        retries: opts.retries,
+    })
+    .ok();
+    Ok(())
+}
+
+fn http() -> &'static Http {
+    HTTP.get_or_init(|| Http {
+        client: Client::new(),
+        token: None,
+        env_token: None,
+        retries: DEFAULT_RETRIES,
+    })
+}
+
+/// run `attempt` until it succeeds, fails for good or runs out of retries,
+/// sleeping with jittered exponential backoff in between; each retry is
+/// announced through `notice`
+fn with_retries<T>(notice: impl Fn(String), mut attempt: impl FnMut() -> Result<T>) -> Result<T> {
+    let retries = http().retries;
+    let mut tries = 0;
+    loop {
+        match attempt() {
+            Err(e) if tries < retries && is_transient(&e) => {
+                let delay = backoff(tries);
+                tries += 1;
+                notice(format!(
+                    "{e}, retrying in {:.1}s ({tries}/{retries})",
+                    delay.as_secs_f32()
+                ));
+                thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 0.5s, 1s, 2s, ... capped at 30s, plus up to 50% of random jitter
+fn backoff(tries: u32) -> Duration {
+    let base = Duration::from_millis(500)
+        .saturating_mul(1 << tries.min(6))
+        .min(Duration::from_secs(30));
+    base + base.mul_f64(fastrand::f64() / 2.0)
+}
+
+/// failures worth another attempt
+fn is_transient(e: &GetnfError) -> bool {
+    match e {
+        GetnfError::Network { source, .. } => {
+            source.is_connect() || source.is_timeout() || source.is_request() || source.is_body()
+        }
+        GetnfError::Interrupted { .. } => true,
+        GetnfError::HttpStatus { status, .. } => {
+            status.is_server_error() || *status == StatusCode::REQUEST_TIMEOUT
+        }
+        _ => false,
+    }
+}
+
+/// whether `url` is on the public GitHub API
+fn is_github_api(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| url.host_str() == Some("api.github.com"))
+}
+
+/// send a GET request and turn any non-success status into an error; the
+/// token is only sent when `auth` is set, so it never reaches download
+/// mirrors, and a token from the environment only reaches api.github.com
+fn send(url: &str, auth: bool) -> Result<Response> {
+    send_with(url, auth, |req| req)
+}
+
+/// like `send`, with extra headers added by `extra`
+fn send_with(
+    url: &str,
+    auth: bool,
+    extra: impl FnOnce(RequestBuilder) -> RequestBuilder,
+) -> Result<Response> {
+    let http = http();
+    let mut req = extra(http.client.get(url).header(USER_AGENT, "getnf"));
+    let token = http
+        .token
+        .as_deref()
+        .or_else(|| http.env_token.as_deref().filter(|_| is_github_api(url)))
+        .filter(|_| auth);
+    if let Some(token) = token {
+        req = req.bearer_auth(token);
+    }
+    let resp = req.send().map_err(|source| GetnfError::Network {
+        url: url.into(),
+        source,
+    })?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        if let Some(e) = rate_limited(&resp, token.is_some()) {
+            return Err(e);
+        }
+        return Err(GetnfError::HttpStatus {
+            url: url.into(),
+            status,
+        });
+    }
+    Ok(resp)
+}
+
+/// GitHub reports both the primary and the secondary rate limit as 403/429,
+/// with either `x-ratelimit-remaining: 0` or a `retry-after`
+fn rate_limited(resp: &Response, authenticated: bool) -> Option<GetnfError> {
+    if !matches!(
+        resp.status(),
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+    ) {
+        return None;
+    }
+    let header =
+        |name: &str| -> Option<i64> { resp.headers().get(name)?.to_str().ok()?.parse().ok() };
+    let retry_after = header("retry-after");
+    if header("x-ratelimit-remaining") != Some(0) && retry_after.is_none() {
+        return None;
+    }
+    let reset = retry_after
+        .map(|secs| Utc::now().timestamp() + secs)
+        .or_else(|| header("x-ratelimit-reset"));
+    let resets_at = reset
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map(|t| t.with_timezone(&Local).format("%H:%M").to_string());
+    Some(GetnfError::RateLimited {
+        resets_at,
+        authenticated,
+    })
+}
+
+fn body(url: &str, resp: Response) -> Result<String> {
+    resp.text().map_err(|source| GetnfError::Network {
+        url: url.into(),
+        source,
+    })
+}
+
+/// retry notices of requests made while no bar is drawn
+fn warn(msg: String) {
+    eprintln!("{msg}");
+}
+
+/// GET a text document
+pub fn text(url: &str) -> Result<String> {
+    with_retries(warn, || body(url, send(url, false)?))
+}
+
+/// GET a json document from the GitHub API
+pub fn request(url: &str) -> Result<Value> {
+    let buf = with_retries(warn, || body(url, send(url, true)?))?;
+    serde_json::from_str::<Value>(&buf).map_err(|e| GetnfError::InvalidResponse {
+        url: url.into(),
+        reason: e.to_string(),
+    })
+}
+
+/// what is known about a partial download, kept next to it so that a later
+/// run can resume it
+struct Partial {
+    /// strong ETag or Last-Modified of the response the bytes came from
+    validator: String,
+    /// full size of the file
+    total: u64,
+}
+
+impl Partial {
+    fn path(dest: &Path) -> PathBuf {
+        let mut path = dest.as_os_str().to_owned();
+        path.push(".meta");
+        path.into()
+    }
+
+    /// the validator of a full response, if it can be resumed later
+    fn of(resp: &Response) -> Option<Self> {
+        let header = |name| resp.headers().get(name)?.to_str().ok();
+        let validator = header(ETAG)
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| header(LAST_MODIFIED))?;
+        Some(Self {
+            validator: validator.into(),
+            total: resp.content_length()?,
+        })
+    }
+
+    fn load(dest: &Path) -> Option<Self> {
+        let meta = fs::read_to_string(Self::path(dest)).ok()?;
+        let (total, validator) = meta.trim_end().split_once(' ')?;
+        Some(Self {
+            validator: validator.into(),
+            total: total.parse().ok()?,
+        })
+    }
+
+    fn save(&self, dest: &Path) -> Result<()> {
+        let path = Self::path(dest);
+        fs::write(&path, format!("{} {}\n", self.total, self.validator))
+            .map_err(|e| GetnfError::io(path, e))
+    }
+
+    fn remove(dest: &Path) {
+        fs::remove_file(Self::path(dest)).ok();
+    }
+
+    /// the server sent exactly the missing bytes of the same file
+    fn resumed_by(&self, offset: u64, headers: &HeaderMap) -> bool {
+        let Some(range) = headers.get(CONTENT_RANGE).and_then(|v| v.to_str().ok()) else {
+            return false;
+        };
+        // bytes <start>-<end>/<total>
+        let parsed = range.strip_prefix("bytes ").and_then(|range| {
+            let (span, total) = range.split_once('/')?;
+            let (start, end) = span.split_once('-')?;
+            Some((
+                start.parse::<u64>().ok()?,
+                end.parse::<u64>().ok()?,
+                total.parse::<u64>().ok()?,
+            ))
+        });
+        parsed == Some((offset, self.total - 1, self.total))
+    }
+}
+
+/// download `url` into `dest`, showing a bar labeled `font` on `progress`
+/// once the size is known; a transfer interrupted now or in an earlier run
+/// resumes where it stopped when the server supports ranges, and starts over
+/// otherwise. Returns the number of bytes transferred
+pub fn download(url: &str, dest: &Path, font: &str, progress: &Progress) -> Result<u64> {
+    let mut bar: Option<ProgressBar> = None;
+    let mut transferred = 0;
+    let notice = |msg: String| progress.warn(msg);
+    let result = with_retries(notice, || {
+        let offset = fs::metadata(dest).map_or(0, |m| m.len());
+        let partial = Partial::load(dest).filter(|p| offset > 0 && offset < p.total);
+        let resp = match &partial {
+            Some(p) => send_with(url, false, |req| {
+                req.header(RANGE, format!("bytes={offset}-"))
+                    .header(IF_RANGE, &p.validator)
+            }),
+            None => send(url, false),
+        };
+        let resp = match resp {
+            // the part is no longer a prefix of the file, start over
+            Err(GetnfError::HttpStatus { status, .. })
+                if status == StatusCode::RANGE_NOT_SATISFIABLE =>
+            {
+                Partial::remove(dest);
+                send(url, false)?
+            }
+            resp => resp?,
+        };
+
+        let resumed = resp.status() == StatusCode::PARTIAL_CONTENT
+            && partial
+                .as_ref()
+                .is_some_and(|p| p.resumed_by(offset, resp.headers()));
+        let resp = if resp.status() == StatusCode::PARTIAL_CONTENT && !resumed {
+            // some other range came back, do not guess and start over
+            Partial::remove(dest);
+            send(url, false)?
+        } else {
+            resp
+        };
+        let (file, start, total) = if resumed {
+            let file = fs::OpenOptions::new()
+                .append(true)
+                .open(dest)
+                .map_err(|e| GetnfError::io(dest, e))?;
+            (file, offset, partial.map(|p| p.total))
+        } else if resp.status() == StatusCode::OK {
+            match Partial::of(&resp) {
+                Some(p) => p.save(dest)?,
+                None => Partial::remove(dest),
+            }
+            let file = fs::File::create(dest).map_err(|e| GetnfError::io(dest, e))?;
+            (file, 0, resp.content_length())
+        } else {
+            return Err(GetnfError::InvalidResponse {
+                url: url.into(),
+                reason: format!("unexpected {} to a range request", resp.status()),
+            });
+        };
+
+        let bar = bar.get_or_insert_with(|| progress.download(font, total));
+        bar.set_position(start);
+        let written = copy(url, resp, file, dest, bar, &mut transferred)?;
+        match total {
+            Some(total) if start + written != total => Err(GetnfError::Interrupted {
+                url: url.into(),
+                source: io::ErrorKind::UnexpectedEof.into(),
+            }),
+            _ => Ok(()),
+        }
+    });
+    if let Some(bar) = bar {
+        match result {
+            Ok(_) => bar.finish(),
+            Err(_) => bar.abandon(),
+        }
+    }
+    if result.is_ok() {
+        Partial::remove(dest);
+    }
+    result.map(|()| transferred)
+}
+
+/// copy the response body to `file`, telling read failures (worth a retry)
+/// from write failures (not)
+fn copy(
+    url: &str,
+    mut resp: Response,
+    mut file: fs::File,
+    dest: &Path,
+    bar: &ProgressBar,
+    transferred: &mut u64,
+) -> Result<u64> {
+    let mut buf = vec![0; 64 * 1024];
+    let mut written = 0;
+    loop {
+        let n = match resp.read(&mut buf) {
+            Ok(0) => return Ok(written),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(source) => {
+                return Err(GetnfError::Interrupted {
+                    url: url.into(),
+                    source,
+                })
+            }
+        };
+        file.write_all(&buf[..n])
+            .map_err(|e| GetnfError::io(dest, e))?;
+        written += n as u64;
+        *transferred += n as u64;
+        bar.inc(n as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn partial() -> Partial {
+        Partial {
+            validator: "\"abc\"".into(),
+            total: 100,
+        }
+    }
+
+    fn content_range(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_RANGE, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn resumed_by_exact_remainder() {
+        assert!(partial().resumed_by(40, &content_range("bytes 40-99/100")));
+    }
+
+    #[test]
+    fn resumed_by_rejects_other_ranges() {
+        let partial = partial();
+        assert!(!partial.resumed_by(40, &content_range("bytes 0-99/100")));
+        assert!(!partial.resumed_by(40, &content_range("bytes 40-89/100")));
+        assert!(!partial.resumed_by(40, &content_range("bytes 40-119/120")));
+    }
+
+    #[test]
+    fn resumed_by_rejects_malformed_content_range() {
+        let partial = partial();
+        for value in [
+            "bytes 40-99/*",
+            "bytes */100",
+            "bytes 40-99",
+            "bytes40-99/100",
+            "items 40-99/100",
+            "bytes -99/100",
+            "bytes 40-x/100",
+            "",
+        ] {
+            assert!(!partial.resumed_by(40, &content_range(value)), "{value}");
+        }
+        assert!(!partial.resumed_by(40, &HeaderMap::new()));
+    }
+
+    #[test]
+    fn env_token_only_for_github_api() {
+        assert!(is_github_api(
+            "https://api.github.com/repos/ryanoasis/nerd-fonts/releases"
+        ));
+        assert!(!is_github_api(
+            "https://mirror.example/api.github.com/releases"
+        ));
+        assert!(!is_github_api(
+            "https://api.github.com.evil.example/releases"
+        ));
+        assert!(!is_github_api("not a url"));
+    }
+}
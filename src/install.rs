@@ -0,0 +1,1007 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Instant,
+};
+
+use indicatif::{HumanBytes, HumanDuration};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    cache::{self, Cache},
+    checksum::Checksums,
+    endpoints::Endpoints,
+    error::{GetnfError, Result},
+    filter::{self, FontFilter, OutlineFormat},
+    manifest::{remove_empty_dirs, InstalledFont, Manifest, Scope},
+    paths::{self, font_dir},
+    progress::Progress,
+    release,
+};
+
+/// which release asset to install a font from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    /// `.tar.xz` when the release has it, `.zip` otherwise
+    #[default]
+    Auto,
+    Zip,
+    #[value(name = "tar.xz")]
+    #[serde(rename = "tar.xz")]
+    TarXz,
+}
+
+impl ArchiveFormat {
+    /// acceptable archive extensions, preferred first
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Self::Auto => &[".tar.xz", ".zip"],
+            Self::Zip => &[".zip"],
+            Self::TarXz => &[".tar.xz"],
+        }
+    }
+}
+
+/// what to install from a release, recorded so that `update` can repeat it
+#[derive(Debug, Clone)]
+pub struct Selection {
+    pub tag: String,
+    /// installed with `--release`, so `update` leaves it alone
+    pub pinned: bool,
+    pub filter: FontFilter,
+    /// outline format kept when a family ships both
+    pub format: OutlineFormat,
+}
+
+/// settings shared by every command that installs or lists fonts
+#[derive(Debug)]
+pub struct InstallOptions {
+    pub endpoints: Endpoints,
+    pub global: bool,
+    pub jobs: usize,
+    pub verify: bool,
+    pub offline: bool,
+    pub archive_format: ArchiveFormat,
+    /// outline format kept when a family ships both
+    pub format: OutlineFormat,
+    pub cache: Cache,
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).map_err(|e| GetnfError::io(path, e))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| GetnfError::io(path, e))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// newest release, taken from the local archives when offline
+pub fn resolve_latest(opts: &InstallOptions) -> Result<String> {
+    if !opts.offline {
+        return release::latest(&opts.endpoints);
+    }
+    opts.cache
+        .local_tags()?
+        .into_iter()
+        .next()
+        .ok_or_else(|| GetnfError::NotAvailableOffline {
+            missing: vec!["any release archive".into()],
+            searched: opts.cache.searched(),
+        })
+}
+
+/// `tag` as the repository spells it, once it is known to exist; offline it
+/// has to be among the local archives
+pub fn resolve_release(tag: &str, opts: &InstallOptions) -> Result<String> {
+    let tag = release::normalize_tag(tag);
+    if opts.offline {
+        if opts.cache.local_tags()?.contains(&tag) {
+            return Ok(tag);
+        }
+        return Err(GetnfError::NotAvailableOffline {
+            missing: vec![format!("release {tag}")],
+            searched: opts.cache.searched(),
+        });
+    }
+    Ok(release::fetch(&opts.endpoints, &tag)?.tag)
+}
+
+/// the archive of `font` in the first of `exts` that is `available`
+fn first_archive(font: &str, exts: &[&str], available: impl Fn(&str) -> bool) -> Option<String> {
+    exts.iter()
+        .map(|ext| format!("{font}{ext}"))
+        .find(|file| available(file))
+}
+
+/// fonts that failed, each with its error
+type Failures = Vec<(String, GetnfError)>;
+
+/// the archive to install each font from: a local one of an acceptable
+/// format if there is one, otherwise the preferred format the release has;
+/// fonts the release has no archive for are returned apart with their error
+fn archive_files(
+    fonts: &[String],
+    tag: &str,
+    opts: &InstallOptions,
+) -> Result<(Vec<(String, String)>, Failures)> {
+    let exts = opts.archive_format.extensions();
+    let mut assets: Option<Vec<String>> = None;
+    let mut files = vec![];
+    let mut missing = vec![];
+    for font in fonts {
+        if let Some(file) = first_archive(font, exts, |file| opts.cache.find(tag, file).is_some()) {
+            files.push((font.clone(), file));
+            continue;
+        }
+        if opts.offline || exts.len() == 1 {
+            files.push((font.clone(), format!("{font}{}", exts[0])));
+            continue;
+        }
+        // only ask for the asset list when something has to be downloaded
+        if assets.is_none() {
+            let release = release::fetch(&opts.endpoints, tag)?;
+            assets = Some(release.assets.into_iter().map(|a| a.name).collect());
+        }
+        let names = assets.as_deref().unwrap_or_default();
+        match first_archive(font, exts, |file| names.iter().any(|name| name == file)) {
+            Some(file) => files.push((font.clone(), file)),
+            None => missing.push((
+                font.clone(),
+                GetnfError::FontNotFound(format!(
+                    "{font} (release {tag} has no {} archive)",
+                    exts.join(" or ")
+                )),
+            )),
+        }
+    }
+    Ok((files, missing))
+}
+
+/// download one font archive and unpack it into `staging`, returning its
+/// manifest record and the number of bytes downloaded
+fn install_font(
+    file: &str,
+    selection: &Selection,
+    staging: &Path,
+    checksums: Option<&Checksums>,
+    opts: &InstallOptions,
+    progress: &Progress,
+) -> Result<(InstalledFont, u64)> {
+    let font = cache::font_name(file).unwrap_or(file);
+    let tag = selection.tag.as_str();
+    let url = opts.endpoints.asset(tag, file);
+    let cache = &opts.cache;
+    let cache_path = cache.archive_path(tag, file);
+    let local = cache.find(tag, file);
+    let cached = local.is_some();
+    let mut archive_path = local.unwrap_or_else(|| cache_path.clone());
+    let download = || cache.download(&url, &cache_path, font, progress);
+    let mut downloaded = 0;
+    if cached {
+        progress.println(format!("using local {}", archive_path.display()));
+    } else if opts.offline {
+        return Err(GetnfError::NotAvailableOffline {
+            missing: vec![format!("{tag}/{file}")],
+            searched: cache.searched(),
+        });
+    } else {
+        downloaded = download()?;
+    }
+
+    let mut sha256 = sha256_file(&archive_path)?;
+    if let Some(checksums) = checksums {
+        let Some(expected) = checksums.get(file) else {
+            return Err(GetnfError::ChecksumMissing(file.into()));
+        };
+        if cached && !opts.offline && expected != sha256 {
+            // corrupt local copy, fetch it again
+            downloaded = download()?;
+            archive_path = cache_path.clone();
+            sha256 = sha256_file(&archive_path)?;
+        }
+        if expected != sha256 {
+            if archive_path == cache_path {
+                fs::remove_file(&archive_path).ok();
+            }
+            return Err(GetnfError::ChecksumMismatch {
+                file: file.into(),
+                expected: expected.into(),
+                actual: sha256,
+            });
+        }
+    }
+    prepare_staging(staging, font)?;
+    let files = unpack_font(font, &archive_path, staging, progress)?;
+    let files = apply_filter(
+        font,
+        files,
+        staging,
+        &selection.filter,
+        selection.format,
+        progress,
+    )?;
+    let (files, docs) = split_docs(font, files, staging)?;
+    progress.println(format!("unpacked {font} {tag} ({} files)", files.len()));
+
+    let record = InstalledFont {
+        release: tag.into(),
+        url,
+        sha256,
+        verified: checksums.is_some(),
+        pinned: selection.pinned,
+        filter: selection.filter.clone(),
+        format: Some(selection.format),
+        installed_at: InstalledFont::now(),
+        scope: Scope::new(opts.global),
+        files,
+        docs,
+    };
+    Ok((record, downloaded))
+}
+
+/// unpack `archive_path` into `dir/font`, returning the files written
+/// relative to `dir`
+fn unpack_font(
+    font: &str,
+    archive_path: &Path,
+    dir: &Path,
+    progress: &Progress,
+) -> Result<Vec<PathBuf>> {
+    let archive_err = |source| GetnfError::Archive {
+        font: font.into(),
+        source,
+    };
+    let mut archive = arkiv::Archive::open(archive_path).map_err(archive_err)?;
+    let mut files = vec![];
+    for entry in archive.entries_iter().map_err(archive_err)? {
+        let entry = entry.map_err(archive_err)?;
+        if entry.is_file() {
+            // tarballs made with `tar -C dir .` prefix every entry with `./`
+            let path = entry
+                .path()
+                .components()
+                .filter(|c| !matches!(c, Component::CurDir))
+                .collect::<PathBuf>();
+            if !is_relative_below(&path) {
+                return Err(archive_err(arkiv::Error::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("entry {} points outside the archive", path.display()),
+                ))));
+            }
+            files.push(Path::new(font).join(path));
+        }
+    }
+    let spinner = progress.extract(font);
+    let unpacked = archive.unpack(dir.join(font)).map_err(|e| match e {
+        arkiv::Error::Io(e) => GetnfError::io(dir.join(font), e),
+        e => archive_err(e),
+    });
+    spinner.finish_and_clear();
+    unpacked?;
+    Ok(files)
+}
+
+/// whether `path` is relative and never climbs out of the directory it is
+/// joined to
+fn is_relative_below(path: &Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// delete the unpacked files of `font` that `filter` rejects, and those in
+/// the outline format not wanted, from `staging`, returning the others
+fn apply_filter(
+    font: &str,
+    files: Vec<PathBuf>,
+    staging: &Path,
+    filter: &FontFilter,
+    format: OutlineFormat,
+    progress: &Progress,
+) -> Result<Vec<PathBuf>> {
+    let (kept, mut dropped): (Vec<_>, Vec<_>) = files.into_iter().partition(|f| filter.keeps(f));
+    if !dropped.is_empty() && !kept.iter().any(|f| filter::is_font(f)) {
+        return Err(GetnfError::InvalidInput(format!(
+            "no font file of {font} matches {}",
+            filter.describe()
+        )));
+    }
+    let duplicates = format.duplicates(&kept);
+    if let Some(ext) = duplicates.first().and_then(|f| f.extension()) {
+        progress.println(format!(
+            "skipped {} .{} file(s) of {font} shipped in both formats (--format both keeps them)",
+            duplicates.len(),
+            ext.to_string_lossy().to_lowercase()
+        ));
+    }
+    let kept = kept
+        .into_iter()
+        .filter(|f| !duplicates.contains(f))
+        .collect::<Vec<_>>();
+    dropped.extend(duplicates);
+    if dropped.is_empty() {
+        return Ok(kept);
+    }
+    for file in &dropped {
+        remove_font_file(&staging.join(file))?;
+    }
+    remove_empty_dirs(&staging.join(font))?;
+    Ok(kept)
+}
+
+/// move the files of `font` that are not fonts (licenses, readmes) from
+/// `staging/font` to `staging/.docs/font`, returning the font files and the
+/// docs, both relative to their root
+fn split_docs(
+    font: &str,
+    files: Vec<PathBuf>,
+    staging: &Path,
+) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let (fonts, docs): (Vec<_>, Vec<_>) = files.into_iter().partition(|f| filter::is_font(f));
+    if fonts.is_empty() {
+        return Err(GetnfError::InvalidInput(format!(
+            "{font} contains no .ttf or .otf file"
+        )));
+    }
+    for doc in &docs {
+        let to = staging.join(DOCS).join(doc);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| GetnfError::io(parent, e))?;
+        }
+        fs::rename(staging.join(doc), &to).map_err(|e| GetnfError::io(to, e))?;
+    }
+    remove_empty_dirs(&staging.join(font))?;
+    Ok((fonts, docs))
+}
+
+/// replace the docs of `font` under `docs` with the ones staged with it
+fn replace_docs(font: &str, staging: &Path, docs: &Path) -> Result<()> {
+    let target = docs.join(font);
+    remove_dir(&target)?;
+    let staged = staging.join(DOCS).join(font);
+    if !staged.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(docs).map_err(|e| GetnfError::io(docs, e))?;
+    if fs::rename(&staged, &target).is_err() {
+        // the data dir may live on another filesystem than the fonts
+        copy_font_dir(font, &staged, docs)?;
+    }
+    Ok(())
+}
+
+/// remove a directory tree, fine if it does not exist
+fn remove_dir(path: &Path) -> Result<()> {
+    match fs::remove_dir_all(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(GetnfError::io(path, e)),
+    }
+}
+
+/// every file below `root`, relative to it
+fn files_under(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut pending = vec![PathBuf::new()];
+    while let Some(rel) = pending.pop() {
+        let from = root.join(&rel);
+        for entry in fs::read_dir(&from).map_err(|e| GetnfError::io(&from, e))? {
+            let entry = entry.map_err(|e| GetnfError::io(&from, e))?;
+            let rel = rel.join(entry.file_name());
+            if entry.path().is_dir() {
+                pending.push(rel);
+            } else {
+                files.push(rel);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// copy an already extracted font directory into `dir/font`, returning the
+/// files written relative to `dir`
+fn copy_font_dir(font: &str, src: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for rel in files_under(src)? {
+        let to = dir.join(font).join(&rel);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| GetnfError::io(parent, e))?;
+        }
+        fs::copy(src.join(&rel), &to).map_err(|e| GetnfError::io(to, e))?;
+        files.push(Path::new(font).join(rel));
+    }
+    Ok(files)
+}
+
+/// where the docs of fonts are staged, under the staging dir
+const DOCS: &str = ".docs";
+
+/// where fonts are unpacked before being moved into `dir`; inside it so that
+/// the final rename never crosses filesystems
+fn staging_dir(dir: &Path) -> PathBuf {
+    dir.join(".getnf-staging")
+}
+
+/// a fresh `staging/font` to unpack into
+fn prepare_staging(staging: &Path, font: &str) -> Result<()> {
+    remove_dir(&staging.join(font))?;
+    remove_dir(&staging.join(DOCS).join(font))?;
+    fs::create_dir_all(staging).map_err(|e| GetnfError::io(staging, e))
+}
+
+/// hold the install lock of a scope until the returned file is dropped, so
+/// that two runs never swap fonts or rewrite the manifest at the same time
+fn lock_scope(global: bool) -> Result<fs::File> {
+    let dir = paths::data_dir(global)?;
+    fs::create_dir_all(&dir).map_err(|e| GetnfError::io(&dir, e))?;
+    let path = dir.join("lock");
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| GetnfError::io(&path, e))?;
+    match file.try_lock() {
+        Ok(()) => return Ok(file),
+        Err(fs::TryLockError::WouldBlock) => {
+            eprintln!("waiting for another getnf run to finish");
+        }
+        Err(fs::TryLockError::Error(e)) => return Err(GetnfError::io(path, e)),
+    }
+    file.lock().map_err(|e| GetnfError::io(path, e))?;
+    Ok(file)
+}
+
+/// clean up after an install that was killed: put back fonts that were moved
+/// aside but never replaced, then drop whatever is left
+fn recover_staging(dir: &Path) -> Result<()> {
+    let staging = staging_dir(dir);
+    let entries = match fs::read_dir(&staging) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(GetnfError::io(staging, e)),
+    };
+    for entry in entries {
+        let entry = entry.map_err(|e| GetnfError::io(&staging, e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(font) = name.strip_suffix(".old") {
+            let live = dir.join(font);
+            if !live.exists() {
+                fs::rename(entry.path(), &live).map_err(|e| GetnfError::io(live, e))?;
+            }
+        }
+    }
+    fs::remove_dir_all(&staging).map_err(|e| GetnfError::io(staging, e))
+}
+
+/// copy files of `backup` that no install of getnf owns (`owned`, relative
+/// to the font dir) into the new `live` directory
+fn carry_over(font: &str, backup: &Path, live: &Path, owned: &[PathBuf]) -> Result<()> {
+    for rel in files_under(backup)? {
+        let to = live.join(&rel);
+        if owned.contains(&Path::new(font).join(&rel)) || to.exists() {
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| GetnfError::io(parent, e))?;
+        }
+        fs::copy(backup.join(&rel), &to).map_err(|e| GetnfError::io(to, e))?;
+    }
+    Ok(())
+}
+
+/// swap the font unpacked in the staging dir with the installed one, move
+/// its docs under `docs` and record it; on any failure the previous version
+/// and record stay in place
+fn commit_install(
+    manifest: &mut Manifest,
+    dir: &Path,
+    docs: &Path,
+    font: &str,
+    record: InstalledFont,
+) -> Result<()> {
+    let staging = staging_dir(dir);
+    let live = dir.join(font);
+    let backup = staging.join(format!("{font}.old"));
+    let had_old = live.exists();
+    if had_old {
+        fs::rename(&live, &backup).map_err(|e| GetnfError::io(&live, e))?;
+    }
+    let restore = || {
+        fs::remove_dir_all(&live).ok();
+        if had_old {
+            fs::rename(&backup, &live).ok();
+        }
+    };
+    if let Err(e) = fs::rename(staging.join(font), &live) {
+        restore();
+        return Err(GetnfError::io(&live, e));
+    }
+
+    let old = manifest.get(font).cloned();
+    if had_old {
+        let owned = old.as_ref().map(|r| r.files.as_slice()).unwrap_or_default();
+        if let Err(e) = carry_over(font, &backup, &live, owned) {
+            restore();
+            return Err(e);
+        }
+    }
+    if let Err(e) = replace_docs(font, &staging, docs) {
+        restore();
+        return Err(e);
+    }
+    manifest.insert(font.into(), record);
+    if let Err(e) = manifest.save() {
+        restore();
+        match old {
+            Some(old) => manifest.insert(font.into(), old),
+            None => {
+                manifest.remove(font);
+            }
+        }
+        return Err(e);
+    }
+    // leftovers are removed by the next install
+    fs::remove_dir_all(&backup).ok();
+    Ok(())
+}
+
+/// font name of a local archive or directory, e.g. `JetBrainsMono.tar.xz`;
+/// besides the release formats, a plain `.tar` can be unpacked too
+fn local_font_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    if path.is_dir() {
+        return Some(name.into());
+    }
+    cache::font_name(&name)
+        .or_else(|| name.strip_suffix(".tar"))
+        .map(Into::into)
+}
+
+/// install a font from a local archive or extracted directory; the font
+/// name is inferred from `path` unless given
+pub fn install_from(
+    path: &Path,
+    font: Option<String>,
+    filter: &FontFilter,
+    opts: &InstallOptions,
+) -> Result<()> {
+    let Some(font) = font.or_else(|| local_font_name(path)) else {
+        return Err(GetnfError::InvalidInput(format!(
+            "{} is neither a directory nor a supported archive",
+            path.display()
+        )));
+    };
+    let meta = fs::metadata(path).map_err(|e| GetnfError::io(path, e))?;
+    let source = fs::canonicalize(path).map_err(|e| GetnfError::io(path, e))?;
+    let dir = font_dir(opts.global)?;
+    let docs_dir = paths::docs_dir(opts.global)?;
+    let _lock = lock_scope(opts.global)?;
+    recover_staging(&dir)?;
+    let staging = staging_dir(&dir);
+    let mut manifest = Manifest::load(opts.global)?;
+    let progress = Progress::new();
+
+    prepare_staging(&staging, &font)?;
+    let staged = if meta.is_dir() {
+        copy_font_dir(&font, path, &staging).map(|files| (files, String::new()))
+    } else {
+        sha256_file(path)
+            .and_then(|sha256| Ok((unpack_font(&font, path, &staging, &progress)?, sha256)))
+    }
+    .and_then(|(files, sha256)| {
+        let files = apply_filter(&font, files, &staging, filter, opts.format, &progress)?;
+        Ok((split_docs(&font, files, &staging)?, sha256))
+    });
+    let ((files, docs), sha256) = match staged {
+        Ok(staged) => staged,
+        Err(e) => {
+            fs::remove_dir_all(&staging).ok();
+            return Err(e);
+        }
+    };
+
+    // archives taken from a cache-like `<tag>/<font>.tar.xz` layout keep their tag
+    let release = path
+        .parent()
+        .and_then(Path::file_name)
+        .map(|tag| tag.to_string_lossy().to_string())
+        .filter(|tag| release::parse_tag(tag).is_some())
+        .unwrap_or_else(|| "local".into());
+    let files_len = files.len();
+    let record = InstalledFont {
+        release,
+        url: format!("file://{}", source.display()),
+        sha256,
+        verified: false,
+        pinned: false,
+        filter: filter.clone(),
+        format: Some(opts.format),
+        installed_at: InstalledFont::now(),
+        scope: Scope::new(opts.global),
+        files,
+        docs,
+    };
+    let committed = commit_install(&mut manifest, &dir, &docs_dir, &font, record);
+    fs::remove_dir_all(&staging).ok();
+    committed?;
+    progress.println(format!(
+        "installed {font} from {} ({files_len} files)",
+        path.display()
+    ));
+    Ok(())
+}
+
+/// install `fonts` as `selection` says, `opts.jobs` fonts at a time; a
+/// failing font does not stop the others
+pub fn install_fonts(fonts: &[String], selection: &Selection, opts: &InstallOptions) -> Result<()> {
+    // two workers on the same font would fight over its staging dir
+    let mut seen = HashSet::new();
+    let fonts = fonts
+        .iter()
+        .filter(|font| seen.insert(font.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    let fonts = fonts.as_slice();
+    if fonts.is_empty() {
+        return Ok(());
+    }
+    let tag = selection.tag.as_str();
+
+    let (files, mut failed) = archive_files(fonts, tag, opts)?;
+    if opts.offline {
+        let missing = files
+            .iter()
+            .filter(|(_, file)| opts.cache.find(tag, file).is_none())
+            .map(|(_, file)| format!("{tag}/{file}"))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(GetnfError::NotAvailableOffline {
+                missing,
+                searched: opts.cache.searched(),
+            });
+        }
+    }
+
+    let dir = font_dir(opts.global)?;
+    let docs_dir = paths::docs_dir(opts.global)?;
+    let _lock = lock_scope(opts.global)?;
+    recover_staging(&dir)?;
+    let staging = staging_dir(&dir);
+    let mut manifest = Manifest::load(opts.global)?;
+    let checksums = match (opts.verify, opts.offline) {
+        (false, _) => None,
+        (true, false) => Some(Checksums::fetch(tag, &opts.endpoints, &opts.cache)?),
+        (true, true) => Some(Checksums::local(tag, &opts.cache)?),
+    };
+    let progress = Progress::new();
+    for (font, e) in &failed {
+        progress.println(format!("failed to install {font}, left untouched: {e}"));
+    }
+    let started = Instant::now();
+    let mut downloaded = 0;
+
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|s| {
+        for _ in 0..opts.jobs.clamp(1, files.len().max(1)) {
+            let (tx, next, files, staging, checksums, progress) = (
+                tx.clone(),
+                &next,
+                &files,
+                &staging,
+                checksums.as_ref(),
+                &progress,
+            );
+            s.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some((font, file)) = files.get(i) else {
+                    break;
+                };
+                let result = install_font(file, selection, staging, checksums, opts, progress);
+                if tx.send((font, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        for (font, result) in rx {
+            let committed = result.and_then(|(record, size)| {
+                downloaded += size;
+                let release = record.release.clone();
+                commit_install(&mut manifest, &dir, &docs_dir, font, record)?;
+                progress.println(format!("installed {font} {release}"));
+                Ok(())
+            });
+            if let Err(e) = committed {
+                progress.println(format!("failed to install {font}, left untouched: {e}"));
+                failed.push((font.clone(), e));
+            }
+        }
+    });
+    fs::remove_dir_all(&staging).ok();
+
+    progress.println(format!(
+        "installed {} font(s), {} downloaded in {}",
+        fonts.len() - failed.len(),
+        HumanBytes(downloaded),
+        HumanDuration(started.elapsed())
+    ));
+    if !failed.is_empty() {
+        return Err(GetnfError::Failed {
+            total: fonts.len(),
+            failed,
+        });
+    }
+    Ok(())
+}
+
+/// what `update` does with one installed font
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UpdateAction {
+    Update,
+    UpToDate,
+    /// installed with `--release`
+    Pinned,
+    /// installed with `--from`
+    Local,
+}
+
+/// what `update` does with `record` when moving fonts to `target`, which
+/// comes from the config pin when `pinned_target`
+fn update_action(
+    record: &InstalledFont,
+    target: &str,
+    pinned_target: bool,
+    ignore_pin: bool,
+) -> UpdateAction {
+    // it came from a file, not from the release it may be tagged with
+    if record.is_local() {
+        return UpdateAction::Local;
+    }
+    if record.pinned && !ignore_pin {
+        return UpdateAction::Pinned;
+    }
+    let stale = if pinned_target {
+        // a pin moves fonts to exactly that release, older or not
+        record.release != target
+    } else {
+        release::is_outdated(&record.release, target)
+    };
+    if stale {
+        UpdateAction::Update
+    } else {
+        UpdateAction::UpToDate
+    }
+}
+
+/// update the given installed fonts to the latest release, or to `pin` when
+/// set, skipping those already on it, those installed from a local file and
+/// those installed with `--release` unless `ignore_pin`; returns the number
+/// of fonts updated
+pub fn update_fonts(
+    fonts: &[String],
+    pin: Option<&str>,
+    ignore_pin: bool,
+    opts: &InstallOptions,
+) -> Result<usize> {
+    if fonts.is_empty() {
+        return Ok(0);
+    }
+
+    let pin = pin.filter(|_| !ignore_pin);
+    let target = match pin {
+        Some(tag) => resolve_release(tag, opts)?,
+        None => resolve_latest(opts)?,
+    };
+    let manifest = Manifest::load(opts.global)?;
+    // fonts sharing a filter and format are installed together
+    let mut batches: Vec<(FontFilter, OutlineFormat, Vec<String>)> = vec![];
+    let mut outdated = 0;
+    let mut pinned = 0;
+    let mut local = 0;
+    for font in fonts {
+        let Some(record) = manifest.get(font) else {
+            return Err(GetnfError::FontNotFound(font.clone()));
+        };
+        match update_action(record, &target, pin.is_some(), ignore_pin) {
+            UpdateAction::Local => {
+                println!("{font}: installed from a local file, skipped");
+                local += 1;
+            }
+            UpdateAction::Pinned => {
+                println!("{font}: pinned to {}", record.release);
+                pinned += 1;
+            }
+            UpdateAction::Update => {
+                let filter = &record.filter;
+                // records from before the format was kept follow the current one
+                let format = record.format.unwrap_or(opts.format);
+                let mut flags = filter.describe();
+                if format != OutlineFormat::default() {
+                    let flag = format!("--format {format:?}").to_lowercase();
+                    flags = [flags, flag].join(" ").trim().into();
+                }
+                match flags {
+                    flags if flags.is_empty() => println!("{font}: {} -> {target}", record.release),
+                    flags => println!("{font}: {} -> {target} ({flags})", record.release),
+                }
+                match batches
+                    .iter_mut()
+                    .find(|(f, o, _)| f == filter && *o == format)
+                {
+                    Some((_, _, batch)) => batch.push(font.clone()),
+                    None => batches.push((filter.clone(), format, vec![font.clone()])),
+                }
+                outdated += 1;
+            }
+            UpdateAction::UpToDate => println!("{font}: up to date ({})", record.release),
+        }
+    }
+
+    let mut failed = vec![];
+    for (filter, format, batch) in batches {
+        let selection = Selection {
+            tag: target.clone(),
+            pinned: false,
+            filter,
+            format,
+        };
+        match install_fonts(&batch, &selection, opts) {
+            Ok(()) => {}
+            Err(GetnfError::Failed { failed: f, .. }) => failed.extend(f),
+            Err(e) => return Err(e),
+        }
+    }
+    println!(
+        "{} updated, {} up to date, {pinned} pinned, {local} local",
+        outdated - failed.len(),
+        fonts.len() - outdated - pinned - local
+    );
+    if !failed.is_empty() {
+        return Err(GetnfError::Failed {
+            total: outdated,
+            failed,
+        });
+    }
+    Ok(outdated)
+}
+
+fn remove_font_file(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(GetnfError::io(path, e)),
+    }
+}
+
+/// remove exactly the files recorded in the manifest
+pub fn uninstall_fonts(fonts: &[String], global: bool) -> Result<()> {
+    let dir = font_dir(global)?;
+    let docs = paths::docs_dir(global)?;
+    let _lock = lock_scope(global)?;
+    let mut manifest = Manifest::load(global)?;
+    for font in fonts {
+        let Some(record) = manifest.get(font) else {
+            return Err(GetnfError::FontNotFound(font.clone()));
+        };
+        // a tampered manifest must not make getnf delete files elsewhere
+        let root = fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+        for file in &record.files {
+            let path = dir.join(file);
+            let escapes = fs::canonicalize(&path).is_ok_and(|real| !real.starts_with(&root));
+            if !is_relative_below(file) || escapes {
+                return Err(GetnfError::io(
+                    path,
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("recorded outside {}, refusing to remove it", dir.display()),
+                    ),
+                ));
+            }
+        }
+        for file in &record.files {
+            remove_font_file(&dir.join(file))?;
+        }
+        remove_empty_dirs(&dir.join(font))?;
+        remove_dir(&docs.join(font))?;
+        manifest.remove(font);
+        manifest.save()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(release: &str) -> InstalledFont {
+        InstalledFont {
+            release: release.into(),
+            url: format!("https://example.com/{release}/Foo.tar.xz"),
+            sha256: String::new(),
+            verified: false,
+            pinned: false,
+            filter: FontFilter::default(),
+            format: None,
+            installed_at: 0,
+            scope: Scope::User,
+            files: vec![],
+            docs: vec![],
+        }
+    }
+
+    #[test]
+    fn updates_only_older_releases() {
+        let action = |release| update_action(&record(release), "v3.10.0", false, false);
+        assert_eq!(action("v3.9.0"), UpdateAction::Update);
+        assert_eq!(action("v3.10.0"), UpdateAction::UpToDate);
+        assert_eq!(action("v3.11.0"), UpdateAction::UpToDate);
+    }
+
+    #[test]
+    fn pinned_fonts_stay_unless_ignored() {
+        let mut font = record("v3.1.1");
+        font.pinned = true;
+        assert_eq!(
+            update_action(&font, "v3.2.1", false, false),
+            UpdateAction::Pinned
+        );
+        assert_eq!(
+            update_action(&font, "v3.2.1", false, true),
+            UpdateAction::Update
+        );
+    }
+
+    #[test]
+    fn config_pin_moves_to_exactly_its_release() {
+        let action = |release| update_action(&record(release), "v3.1.1", true, false);
+        assert_eq!(action("v3.2.1"), UpdateAction::Update);
+        assert_eq!(action("v3.0.0"), UpdateAction::Update);
+        assert_eq!(action("v3.1.1"), UpdateAction::UpToDate);
+    }
+
+    #[test]
+    fn local_installs_are_never_updated() {
+        // a `--from` archive taken from a `<tag>/` dir keeps that tag
+        let mut font = record("v3.1.1");
+        font.url = "file:///mirror/v3.1.1/Foo.tar.xz".into();
+        assert_eq!(
+            update_action(&font, "v3.2.1", false, true),
+            UpdateAction::Local
+        );
+    }
+
+    #[test]
+    fn infers_local_font_names() {
+        let name = |file| local_font_name(Path::new(file));
+        assert_eq!(name("/tmp/Foo.tar.xz").as_deref(), Some("Foo"));
+        assert_eq!(name("/tmp/Foo.zip").as_deref(), Some("Foo"));
+        assert_eq!(name("/tmp/Foo.tar").as_deref(), Some("Foo"));
+        assert_eq!(name("/tmp/Foo.rar"), None);
+    }
+
+    #[test]
+    fn auto_prefers_tar_xz_and_falls_back_to_zip() {
+        let assets = ["Foo.tar.xz", "Foo.zip", "Bar.zip", "SHA-256.txt"];
+        let pick = |font, format: ArchiveFormat| {
+            first_archive(font, format.extensions(), |file| assets.contains(&file))
+        };
+        assert_eq!(
+            pick("Foo", ArchiveFormat::Auto).as_deref(),
+            Some("Foo.tar.xz")
+        );
+        assert_eq!(pick("Bar", ArchiveFormat::Auto).as_deref(), Some("Bar.zip"));
+        assert_eq!(pick("Foo", ArchiveFormat::Zip).as_deref(), Some("Foo.zip"));
+        assert_eq!(pick("Bar", ArchiveFormat::TarXz), None);
+        assert_eq!(pick("Baz", ArchiveFormat::Auto), None);
+    }
+}
@@ -1,12 +1,37 @@
-use std::{env, fs, io::Read, path::PathBuf};
+use std::{env, path::PathBuf, process::ExitCode, time::Duration};
 
 use clap::{Parser, Subcommand};
 use dialoguer::MultiSelect;
-use reqwest::{header::USER_AGENT, IntoUrl};
-use serde_json::Value;
+use indicatif::HumanBytes;
 
-const NERD_FONTS_API: &str = "https://api.github.com/repos/ryanoasis/nerd-fonts";
-const NERD_FONTS_REPO: &str = "https://github.com/ryanoasis/nerd-fonts";
+use crate::{
+    cache::Cache,
+    config::Config,
+    endpoints::Endpoints,
+    error::{GetnfError, Result},
+    filter::{FontFilter, OutlineFormat, Variant},
+    http::{HttpOptions, TlsRoots, DEFAULT_CONNECT_TIMEOUT, DEFAULT_RETRIES, DEFAULT_TIMEOUT},
+    install::{
+        install_fonts, install_from, resolve_latest, resolve_release, uninstall_fonts,
+        update_fonts, ArchiveFormat, InstallOptions, Selection,
+    },
+    manifest::{Manifest, Scope},
+};
+
+mod cache;
+mod checksum;
+mod config;
+mod endpoints;
+mod error;
+mod filter;
+mod fontconfig;
+mod fontinfo;
+mod http;
+mod install;
+mod manifest;
+mod paths;
+mod progress;
+mod release;
 
 /// install nerd fonts
 #[derive(Debug, Parser)]
@@ -17,13 +42,70 @@ struct Cli {
     /// install/uninstall/list/update Nerd Fonts for all users
     #[arg(short, long)]
     global: bool,
+    /// only for the current user, overriding `scope = "global"` in the config
+    #[arg(long, conflicts_with = "global")]
+    user: bool,
+    /// number of fonts downloaded and unpacked at the same time [default: 4]
+    #[arg(short, long, value_parser = clap::value_parser!(u8).range(1..))]
+    jobs: Option<u8>,
+    /// install archives even if they do not match the release's checksums
+    #[arg(long)]
+    skip_verify: bool,
+    /// do not run fc-cache after installing, updating or uninstalling
+    #[arg(long)]
+    no_cache_refresh: bool,
+    /// never touch the network, only use the cache and --archive-dir
+    #[arg(long)]
+    offline: bool,
+    /// release asset to install from; auto prefers tar.xz and falls back to
+    /// zip [default: auto]
+    #[arg(long, value_name = "FORMAT")]
+    archive_format: Option<ArchiveFormat>,
+    /// outline format to install when a family ships both ttf and otf
+    /// [default: ttf]
+    #[arg(long, value_name = "FORMAT")]
+    format: Option<OutlineFormat>,
+    /// GitHub API base of the Nerd Fonts repository
+    #[arg(long, value_name = "URL")]
+    api_url: Option<String>,
+    /// repository URL, or asset URL template using {tag}, {file} and {font}
+    #[arg(long, value_name = "URL")]
+    download_url: Option<String>,
+    /// extra attempts after a transient network failure [default: 3]
+    #[arg(long, value_name = "N")]
+    retries: Option<u32>,
+    /// seconds to wait for a connection [default: 10]
+    #[arg(long, value_name = "SECS")]
+    connect_timeout: Option<u64>,
+    /// seconds a single read may stall [default: 30]
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+    /// proxy for every request: http://, https://, socks5:// or socks5h://
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+    /// comma separated hosts reached without --proxy [default: $NO_PROXY]
+    #[arg(long, value_name = "HOSTS")]
+    no_proxy: Option<String>,
+    /// PEM bundle of extra CA certificates to trust
+    #[arg(long, value_name = "FILE")]
+    cacert: Option<PathBuf>,
+    /// only trust the platform's or only getnf's bundled root certificates
+    #[arg(long, value_name = "ROOTS")]
+    tls_roots: Option<TlsRoots>,
+    /// extra directory holding archives as `<tag>/<font>.tar.xz`, searched after the cache
+    #[arg(long, value_name = "DIR")]
+    archive_dir: Vec<PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
 enum Commands {
     /// show the list of installed Nerd Fonts
     #[command(short_flag = 'l')]
-    ListInstalled,
+    ListInstalled {
+        /// list every file of each family
+        #[arg(long)]
+        files: bool,
+    },
     /// show the list of all Nerd Fonts
     #[command(short_flag = 'L')]
     ListAll,
@@ -33,6 +115,20 @@ enum Commands {
         /// font name
         #[arg(short)]
         fonts: Option<String>,
+        /// install from a local archive or extracted directory instead of
+        /// downloading; `-f` then names the font
+        #[arg(long, value_name = "PATH")]
+        from: Option<PathBuf>,
+        /// install this release instead of the latest, e.g. v3.1.1; the fonts
+        /// are then left alone by `update`
+        #[arg(long, value_name = "TAG", conflicts_with = "from")]
+        release: Option<String>,
+        /// only install these variants: default, mono, propo
+        #[arg(long, value_name = "VARIANTS", value_delimiter = ',')]
+        variant: Vec<Variant>,
+        /// only install these styles, e.g. Regular,Bold,Italic
+        #[arg(long, value_name = "STYLES", value_delimiter = ',')]
+        style: Vec<String>,
     },
     /// uninstall the specified Nerd Fonts
     #[command(short_flag = 'u')]
@@ -44,184 +140,634 @@ enum Commands {
     /// update all installed Nerd Fonts
     #[command(short_flag = 'U')]
     Update {
-        /// font name
+        /// font name, defaults to every font installed by getnf
         #[arg(short)]
         fonts: Option<String>,
+        /// update to the latest release, even fonts installed with --release
+        /// and despite `release` in the config
+        #[arg(long)]
+        ignore_pin: bool,
+    },
+    /// show the licenses and readmes shipped with an installed font
+    Docs {
+        /// font name
+        font: String,
+        /// only print where they are stored
+        #[arg(long)]
+        path: bool,
+    },
+    /// show what an installed font consists of, or what a release offers
+    /// for one that is not installed
+    Info {
+        /// font name
+        font: String,
+    },
+    /// manage the downloaded archive cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// list the Nerd Fonts releases
+    Releases {
+        #[command(subcommand)]
+        command: Option<ReleasesCommands>,
+    },
+    /// inspect and edit the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
     },
 }
 
-fn request(url: impl IntoUrl) -> Value {
-    let client = reqwest::blocking::Client::new();
-    let mut resp = client.get(url).header(USER_AGENT, "getnf").send().unwrap();
-    let mut buf = String::new();
-    resp.read_to_string(&mut buf).ok();
-    serde_json::from_str::<Value>(&buf).unwrap()
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    /// show the effective value of a key
+    Get { key: String },
+    /// set a key in the user config file, or the system one with --global
+    Set { key: String, value: String },
+    /// remove a key from the user config file, or the system one with --global
+    Unset { key: String },
+    /// show every key that is set, after layering
+    List,
+    /// show where the config files live
+    Path,
 }
 
-/// font dir
-fn font_dir(global: bool) -> PathBuf {
-    match std::env::consts::OS {
-        "linux" => {
-            if global {
-                "/usr/local/share/fonts/".into()
-            } else {
-                let xdg_data_home = env::var("XDG_DATA_HOME")
-                    .unwrap_or_else(|_| format!("{}/.local/share", env::var("HOME").unwrap()));
-                PathBuf::from(xdg_data_home).join("fonts")
-            }
-        }
-        "macos" => {
-            if global {
-                "/Library/Fonts".into()
-            } else {
-                PathBuf::from(env::var("HOME").unwrap()).join("Library/Fonts")
-            }
-        }
-        "windows" => {
-            if global {
-                let windir = env::var("WINDIR").unwrap_or_else(|_| "C:\\Windows".to_string());
-                PathBuf::from(windir).join("Fonts")
-            } else {
-                let local_appdata =
-                    std::env::var("LOCALAPPDATA").expect("未找到 LOCALAPPDATA 环境变量");
-                PathBuf::from(local_appdata)
-                    .join("Microsoft")
-                    .join("Windows")
-                    .join("Fonts")
-            }
-        }
-        _ => "".into(),
-    }
+#[derive(Debug, Subcommand)]
+enum ReleasesCommands {
+    /// show the notes and assets of a release
+    Show { tag: String },
 }
 
-fn latest_release_version() -> String {
-    let body = request(NERD_FONTS_API.to_string() + "/releases/latest");
-    let tag = &body["tag_name"];
-    tag.as_str().unwrap().into()
+#[derive(Debug, Subcommand)]
+enum CacheCommands {
+    /// show the cached archives
+    List,
+    /// show the total size of the cache
+    Size,
+    /// remove every cached archive
+    Clean,
+    /// remove the archives of old releases
+    Prune {
+        /// number of most recent releases to keep
+        #[arg(long, value_name = "N", num_args = 0..=1, default_value_t = 1, default_missing_value = "1")]
+        keep_latest: usize,
+    },
 }
 
-fn list_installed_fonts(global: bool) -> Vec<String> {
-    let dir = font_dir(global);
-    let dirs = fs::read_dir(dir).unwrap();
-    let mut fds = vec![];
-    for dir in dirs {
-        fds.push(dir.unwrap().file_name().to_string_lossy().to_string());
+/// every font that can be installed, from the local archives when offline
+fn available_fonts(opts: &InstallOptions) -> Result<Vec<String>> {
+    if !opts.offline {
+        return list_remote_fonts(&opts.endpoints);
     }
-    fds
+    let mut fonts = vec![];
+    for tag in opts.cache.local_tags()? {
+        fonts.extend(opts.cache.local_fonts(&tag)?);
+    }
+    fonts.sort();
+    fonts.dedup();
+    Ok(fonts)
+}
+
+fn list_installed_fonts(global: bool) -> Result<Vec<String>> {
+    Ok(Manifest::load(global)?.names())
 }
 
-fn list_remote_fonts() -> Vec<String> {
-    let body = request(NERD_FONTS_API.to_string() + "/contents/patched-fonts?ref=master");
-    let body = body.as_array().unwrap();
+fn list_remote_fonts(endpoints: &Endpoints) -> Result<Vec<String>> {
+    let url = endpoints.api("/contents/patched-fonts?ref=master");
+    let body = http::request(&url)?;
+    let invalid = |reason: &str| GetnfError::InvalidResponse {
+        url: url.clone(),
+        reason: reason.into(),
+    };
+    let body = body
+        .as_array()
+        .ok_or_else(|| invalid("expected an array"))?;
     let mut fonts = vec![];
     for font in body {
-        fonts.push(font["name"].as_str().unwrap().into());
+        let name = font["name"]
+            .as_str()
+            .ok_or_else(|| invalid("entry without name"))?;
+        fonts.push(name.into());
+    }
+    Ok(fonts)
+}
+
+/// `--variant` and `--style`, each falling back to the config when not given
+fn font_filter(variants: Vec<Variant>, styles: Vec<String>, config: &Config) -> FontFilter {
+    FontFilter {
+        variants: match variants {
+            v if v.is_empty() => config.variant.clone().unwrap_or_default(),
+            v => v,
+        },
+        styles: match styles {
+            s if s.is_empty() => config.style.clone().unwrap_or_default(),
+            s => s,
+        },
+    }
+}
+
+/// make sure every requested font is one of the known fonts
+fn check_fonts(fonts: &[String], known: &[String]) -> Result<()> {
+    match fonts.iter().find(|f| !known.contains(f)) {
+        Some(font) => Err(GetnfError::FontNotFound(font.clone())),
+        None => Ok(()),
     }
-    fonts
 }
 
-fn install_fonts(fonts: &[String], global: bool) {
+/// split the `-f` argument into font names, expanding `@group`s
+fn parse_fonts(fonts: &str, config: &Config) -> Result<Vec<String>> {
+    let fonts = fonts
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect::<Vec<_>>();
+    let fonts = config.expand_groups(fonts)?;
     if fonts.is_empty() {
-        return;
+        return Err(GetnfError::InvalidInput("no font name given".into()));
     }
+    Ok(fonts)
+}
 
-    let latest = latest_release_version();
+/// installed fonts with the families their files declare
+fn show_installed(global: bool, list_files: bool) -> Result<()> {
+    let manifest = Manifest::load(global)?;
+    let dir = paths::font_dir(global)?;
+    for font in manifest.names() {
+        let Some(record) = manifest.get(&font) else {
+            continue;
+        };
+        println!("{font} {}", record.release);
+        let (families, unreadable) = fontinfo::families(&dir, &record.files);
+        for family in families {
+            if !list_files {
+                let styles = family
+                    .faces
+                    .iter()
+                    .map(|(info, _)| info.subfamily.as_str())
+                    .collect::<Vec<_>>();
+                println!("  {}: {}", family.label(), styles.join(", "));
+                continue;
+            }
+            println!("  {}", family.label());
+            for (info, file) in &family.faces {
+                println!(
+                    "    {}\t{}\t{}",
+                    info.subfamily,
+                    info.postscript.as_deref().unwrap_or("-"),
+                    file.display()
+                );
+            }
+        }
+        if !unreadable.is_empty() {
+            println!("  unreadable: {} file(s)", unreadable.len());
+            if list_files {
+                unreadable
+                    .iter()
+                    .for_each(|f| println!("    {}", f.display()));
+            }
+        }
+    }
+    Ok(())
+}
 
-    for font in fonts {
-        let mut file_name = PathBuf::new();
-        file_name.push(font.to_string() + ".tar.xz");
-        let url = NERD_FONTS_REPO.to_string()
-            + "/releases/download/"
-            + &latest
-            + "/"
-            + file_name.to_string_lossy().to_string().as_ref();
+/// what an installed font consists of, or the archives of one that is not
+fn show_info(font: &str, release: Option<&str>, opts: &InstallOptions) -> Result<()> {
+    let manifest = Manifest::load(opts.global)?;
+    let Some(record) = manifest.get(font) else {
+        return show_remote_info(font, release, opts);
+    };
+    let font_dir = paths::font_dir(opts.global)?;
+    let mut size = 0;
+    let mut present = vec![];
+    let mut missing = 0;
+    for file in &record.files {
+        let path = font_dir.join(file);
+        match std::fs::metadata(&path) {
+            Ok(meta) => {
+                size += meta.len();
+                present.push(file.clone());
+            }
+            // removed by hand since the install
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => missing += 1,
+            Err(e) => return Err(GetnfError::io(path, e)),
+        }
+    }
+    println!("{font}");
+    println!("  path:     {}", font_dir.join(font).display());
+    println!(
+        "  scope:    {}",
+        format!("{:?}", record.scope).to_lowercase()
+    );
+    println!(
+        "  release:  {}{}",
+        record.release,
+        if record.pinned { " (pinned)" } else { "" }
+    );
+    if record.filter != FontFilter::default() {
+        println!("  filter:   {}", record.filter.describe());
+    }
+    println!("  files:    {} ({})", record.files.len(), HumanBytes(size));
+    let (families, unreadable) = fontinfo::families(&font_dir, &present);
+    println!("  families:");
+    for family in families {
+        let styles = family
+            .faces
+            .iter()
+            .map(|(info, _)| info.subfamily.as_str())
+            .collect::<Vec<_>>();
+        println!("    {}: {}", family.label(), styles.join(", "));
+    }
+    if !unreadable.is_empty() {
+        println!("    unreadable: {} file(s)", unreadable.len());
+    }
+    if missing > 0 {
+        println!("    missing: {missing} file(s)");
+    }
+    let docs_dir = paths::docs_dir(opts.global)?;
+    match record.docs.as_slice() {
+        [] => println!("  license:  none shipped"),
+        docs => {
+            println!("  license:");
+            docs.iter()
+                .for_each(|doc| println!("    {}", docs_dir.join(doc).display()));
+        }
+    }
+    Ok(())
+}
 
-        let mut archive = arkiv::Archive::download(url).unwrap();
-        archive.unpack(font_dir(global).join(font)).unwrap();
+/// archives of a font that is not installed: the local ones when offline,
+/// otherwise the assets of the configured or latest release
+fn show_remote_info(font: &str, release: Option<&str>, opts: &InstallOptions) -> Result<()> {
+    if opts.offline {
+        let archives = opts
+            .cache
+            .archives()?
+            .into_iter()
+            .filter(|a| cache::font_name(&a.file) == Some(font))
+            .collect::<Vec<_>>();
+        if archives.is_empty() {
+            return Err(GetnfError::NotAvailableOffline {
+                missing: vec![format!("{font} archive")],
+                searched: opts.cache.searched(),
+            });
+        }
+        println!("{font} (not installed)");
+        println!("  local archives:");
+        for archive in archives {
+            println!(
+                "    {}/{}\t{}",
+                archive.tag,
+                archive.file,
+                HumanBytes(archive.size)
+            );
+        }
+        return Ok(());
+    }
+    check_fonts(&[font.into()], &list_remote_fonts(&opts.endpoints)?)?;
+    let tag = match release {
+        Some(tag) => release::normalize_tag(tag),
+        None => release::latest(&opts.endpoints)?,
+    };
+    let release = release::fetch(&opts.endpoints, &tag)?;
+    println!("{font} (not installed)");
+    println!("  release:  {} ({})", release.tag, release.date());
+    println!("  archives:");
+    let assets = release
+        .assets
+        .iter()
+        .filter(|a| cache::font_name(&a.name) == Some(font))
+        .collect::<Vec<_>>();
+    if assets.is_empty() {
+        println!("    none in this release");
     }
+    for asset in assets {
+        println!("    {}\t{}", asset.name, HumanBytes(asset.size));
+    }
+    Ok(())
 }
 
-fn uninstall_fonts(fonts: &[String], global: bool) {
-    for font in fonts {
-        fs::remove_dir_all(font_dir(global).join(font)).ok();
+/// print the docs of an installed font, or where they live
+fn show_docs(font: &str, global: bool, path_only: bool) -> Result<()> {
+    let manifest = Manifest::load(global)?;
+    let Some(record) = manifest.get(font) else {
+        return Err(GetnfError::FontNotFound(font.into()));
+    };
+    let dir = paths::docs_dir(global)?;
+    if path_only {
+        println!("{}", dir.join(font).display());
+        return Ok(());
+    }
+    if record.docs.is_empty() {
+        println!("{font} ships no license or readme");
+    }
+    for (i, doc) in record.docs.iter().enumerate() {
+        let path = dir.join(doc);
+        let text = std::fs::read(&path).map_err(|e| GetnfError::io(&path, e))?;
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "==> {} <==",
+            doc.strip_prefix(font).unwrap_or(doc).display()
+        );
+        println!("{}", String::from_utf8_lossy(&text).trim_end());
     }
+    Ok(())
 }
 
-fn main() {
-    let cli = Cli::parse();
-    match cli.command {
-        Commands::ListInstalled => {
-            list_installed_fonts(cli.global)
-                .into_iter()
-                .for_each(|f| println!("{f}"));
+/// config values as typed on the command line
+fn display_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(display_value)
+            .collect::<Vec<_>>()
+            .join(","),
+        value => value.to_string(),
+    }
+}
+
+fn run_config(command: ConfigCommands, global: bool) -> Result<()> {
+    let path = if global {
+        Config::system_path()?
+    } else {
+        Config::path()?
+    };
+    match command {
+        ConfigCommands::Get { key } => {
+            if let Some(value) = Config::load()?.get(&key)? {
+                println!("{}", display_value(&value));
+            }
+        }
+        ConfigCommands::Set { key, value } => config::set(&path, &key, &value)?,
+        ConfigCommands::Unset { key } => config::unset(&path, &key)?,
+        ConfigCommands::List => {
+            for (key, value) in Config::load()?.entries()? {
+                println!("{key} = {}", display_value(&value));
+            }
         }
+        ConfigCommands::Path => {
+            println!("system: {}", Config::system_path()?.display());
+            println!("user: {}", Config::path()?.display());
+        }
+    }
+    Ok(())
+}
+
+fn choose_fonts(fonts: Vec<String>) -> Result<Vec<String>> {
+    let selection = MultiSelect::new()
+        .with_prompt("choose fonts")
+        .items(&fonts)
+        .interact()?;
+    Ok(fonts
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| selection.contains(i))
+        .map(|(_, f)| f)
+        .collect::<Vec<_>>())
+}
+
+fn run(cli: Cli) -> Result<()> {
+    if let Commands::Config { command } = cli.command {
+        return run_config(command, cli.global);
+    }
+
+    let config = Config::load()?;
+    let token = config.github_token.clone().filter(|t| !t.is_empty());
+    let env_token = env::var("GITHUB_TOKEN")
+        .ok()
+        .or_else(|| env::var("GH_TOKEN").ok())
+        .filter(|t| !t.is_empty());
+    let secs = |cli: Option<u64>, config: Option<u64>, default| {
+        Duration::from_secs(cli.or(config).unwrap_or(default))
+    };
+    http::init(HttpOptions {
+        proxy: cli.proxy.or(config.proxy.clone()),
+        no_proxy: cli.no_proxy.or(config.no_proxy.clone()),
+        cacert: cli.cacert.or(config.cacert.clone()),
+        tls_roots: cli.tls_roots.or(config.tls_roots),
+        token,
+        env_token,
+        retries: cli.retries.or(config.retries).unwrap_or(DEFAULT_RETRIES),
+        connect_timeout: secs(
+            cli.connect_timeout,
+            config.connect_timeout,
+            DEFAULT_CONNECT_TIMEOUT,
+        ),
+        timeout: secs(cli.timeout, config.timeout, DEFAULT_TIMEOUT),
+    })?;
+    let global = cli.global || (!cli.user && config.scope == Some(Scope::Global));
+    let opts = InstallOptions {
+        endpoints: Endpoints::new(
+            cli.api_url.or(config.api_url.clone()),
+            cli.download_url.or(config.download_url.clone()),
+        ),
+        global,
+        jobs: cli.jobs.or(config.jobs).unwrap_or(4).into(),
+        verify: !cli.skip_verify,
+        offline: cli.offline,
+        archive_format: cli
+            .archive_format
+            .or(config.archive_format)
+            .unwrap_or_default(),
+        format: cli.format.or(config.format).unwrap_or_default(),
+        cache: Cache::open()?.with_archive_dirs(cli.archive_dir),
+    };
+    // once per command, after the fonts changed
+    let refresh_font_cache = || -> Result<()> {
+        if !cli.no_cache_refresh {
+            fontconfig::refresh(&paths::font_dir(opts.global)?);
+        }
+        Ok(())
+    };
+    match cli.command {
+        Commands::ListInstalled { files } => show_installed(opts.global, files)?,
         Commands::ListAll => {
-            list_remote_fonts()
+            available_fonts(&opts)?
                 .into_iter()
                 .for_each(|f| println!("{f}"));
         }
-        Commands::Install { fonts } => {
+        Commands::Install {
+            fonts,
+            from: Some(path),
+            variant,
+            style,
+            ..
+        } => {
+            let font = match fonts
+                .as_deref()
+                .map(|fonts| parse_fonts(fonts, &config))
+                .transpose()?
+            {
+                Some(fonts) if fonts.len() > 1 => {
+                    return Err(GetnfError::InvalidInput(
+                        "--from installs a single font".into(),
+                    ))
+                }
+                fonts => fonts.and_then(|f| f.into_iter().next()),
+            };
+            let filter = font_filter(variant, style, &config);
+            let result = install_from(&path, font, &filter, &opts);
+            refresh_font_cache()?;
+            result?;
+        }
+        Commands::Install {
+            fonts,
+            from: None,
+            release,
+            variant,
+            style,
+        } => {
+            let pinned = release.is_some();
+            let tag = match release.or(config.release.clone()) {
+                Some(tag) => resolve_release(&tag, &opts)?,
+                None => resolve_latest(&opts)?,
+            };
             let choosed_fonts = if let Some(fonts) = fonts {
-                fonts.split(',').map(|f| f.to_string()).collect::<Vec<_>>()
-            } else {
-                let fonts = list_remote_fonts();
-
-                let selection = MultiSelect::new()
-                    .with_prompt("choose fonts")
-                    .items(&fonts)
-                    .interact()
-                    .unwrap();
+                let fonts = parse_fonts(&fonts, &config)?;
+                // offline, install_fonts reports every missing archive at once
+                if !opts.offline {
+                    check_fonts(&fonts, &list_remote_fonts(&opts.endpoints)?)?;
+                }
                 fonts
-                    .into_iter()
-                    .enumerate()
-                    .filter(|(i, _)| selection.contains(i))
-                    .map(|(_, f)| f)
-                    .collect::<Vec<_>>()
+            } else if opts.offline {
+                choose_fonts(opts.cache.local_fonts(&tag)?)?
+            } else {
+                choose_fonts(list_remote_fonts(&opts.endpoints)?)?
             };
 
-            install_fonts(&choosed_fonts, cli.global);
+            let selection = Selection {
+                tag,
+                pinned,
+                filter: font_filter(variant, style, &config),
+                format: opts.format,
+            };
+            let result = install_fonts(&choosed_fonts, &selection, &opts);
+            refresh_font_cache()?;
+            result?;
         }
-        Commands::Uninstall { fonts } => {
+        Commands::Update { fonts, ignore_pin } => {
+            let installed = list_installed_fonts(opts.global)?;
             let choosed_fonts = if let Some(fonts) = fonts {
-                fonts.split(',').map(|f| f.to_string()).collect::<Vec<_>>()
-            } else {
-                let fonts = list_installed_fonts(cli.global);
-
-                let selection = MultiSelect::new()
-                    .with_prompt("choose fonts")
-                    .items(&fonts)
-                    .interact()
-                    .unwrap();
+                let fonts = parse_fonts(&fonts, &config)?;
+                check_fonts(&fonts, &installed)?;
                 fonts
-                    .into_iter()
-                    .enumerate()
-                    .filter(|(i, _)| selection.contains(i))
-                    .map(|(_, f)| f)
-                    .collect::<Vec<_>>()
+            } else {
+                installed
             };
 
-            uninstall_fonts(&choosed_fonts, cli.global);
+            let result = update_fonts(&choosed_fonts, config.release.as_deref(), ignore_pin, &opts);
+            if !matches!(result, Ok(0)) {
+                refresh_font_cache()?;
+            }
+            result?;
         }
-        Commands::Update { fonts } => {
+        Commands::Uninstall { fonts } => {
+            let installed = list_installed_fonts(opts.global)?;
             let choosed_fonts = if let Some(fonts) = fonts {
-                fonts.split(',').map(|f| f.to_string()).collect::<Vec<_>>()
-            } else {
-                let fonts = list_remote_fonts();
-
-                let selection = MultiSelect::new()
-                    .with_prompt("choose fonts")
-                    .items(&fonts)
-                    .interact()
-                    .unwrap();
+                let fonts = parse_fonts(&fonts, &config)?;
+                check_fonts(&fonts, &installed)?;
                 fonts
-                    .into_iter()
-                    .enumerate()
-                    .filter(|(i, _)| selection.contains(i))
-                    .map(|(_, f)| f)
-                    .collect::<Vec<_>>()
+            } else {
+                choose_fonts(installed)?
             };
 
-            install_fonts(&choosed_fonts, cli.global);
+            let result = uninstall_fonts(&choosed_fonts, opts.global);
+            refresh_font_cache()?;
+            result?;
+        }
+        Commands::Releases { command: None } if opts.offline => {
+            for tag in opts.cache.local_tags()? {
+                let fonts = opts.cache.local_fonts(&tag)?;
+                println!("{tag}\t{} local archives", fonts.len());
+            }
+        }
+        Commands::Releases { command: Some(_) } if opts.offline => {
+            return Err(GetnfError::NotAvailableOffline {
+                missing: vec!["release notes".into()],
+                searched: opts.cache.searched(),
+            });
+        }
+        Commands::Releases { command: None } => {
+            for release in release::list(&opts.endpoints)? {
+                println!(
+                    "{}\t{}\t{} assets\t{}{}",
+                    release.tag,
+                    release.date(),
+                    release.assets.len(),
+                    HumanBytes(release.size()),
+                    if release.prerelease {
+                        "\tprerelease"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        Commands::Releases {
+            command: Some(ReleasesCommands::Show { tag }),
+        } => {
+            let release = release::fetch(&opts.endpoints, &release::normalize_tag(&tag))?;
+            println!(
+                "{} ({}{})",
+                release.tag,
+                release.date(),
+                if release.prerelease {
+                    ", prerelease"
+                } else {
+                    ""
+                }
+            );
+            if let Some(notes) = release
+                .body
+                .as_deref()
+                .map(str::trim)
+                .filter(|n| !n.is_empty())
+            {
+                println!("\n{notes}\n");
+            }
+            for asset in &release.assets {
+                println!("{}\t{}", asset.name, HumanBytes(asset.size));
+            }
+        }
+        Commands::Docs { font, path } => show_docs(&font, opts.global, path)?,
+        Commands::Info { font } => show_info(&font, config.release.as_deref(), &opts)?,
+        Commands::Cache { command } => {
+            let cache = &opts.cache;
+            match command {
+                CacheCommands::List => {
+                    for archive in cache.archives()? {
+                        println!(
+                            "{}/{}\t{}",
+                            archive.tag,
+                            archive.file,
+                            HumanBytes(archive.size)
+                        );
+                    }
+                }
+                CacheCommands::Size => println!("{}", HumanBytes(cache.size()?)),
+                CacheCommands::Clean => println!("freed {}", HumanBytes(cache.clean()?)),
+                CacheCommands::Prune { keep_latest } => {
+                    println!("freed {}", HumanBytes(cache.prune(keep_latest)?))
+                }
+            }
+        }
+        // handled before loading the config, which it may have to fix
+        Commands::Config { .. } => {}
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(e.exit_code())
         }
     }
 }
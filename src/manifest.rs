@@ -0,0 +1,156 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{GetnfError, Result},
+    filter::{FontFilter, OutlineFormat},
+    paths,
+};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    User,
+    Global,
+}
+
+impl Scope {
+    pub fn new(global: bool) -> Self {
+        if global {
+            Self::Global
+        } else {
+            Self::User
+        }
+    }
+}
+
+/// what getnf knows about one font it installed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledFont {
+    /// release tag the archive came from, e.g. `v3.2.1`
+    pub release: String,
+    /// where the archive was downloaded from
+    pub url: String,
+    /// sha256 of the archive, hex encoded
+    pub sha256: String,
+    /// whether `sha256` was checked against the release's published checksums
+    #[serde(default)]
+    pub verified: bool,
+    /// installed with `--release`, so `update` leaves it alone
+    #[serde(default)]
+    pub pinned: bool,
+    /// variants and styles picked at install time
+    #[serde(default)]
+    pub filter: FontFilter,
+    /// outline format kept when a family ships both, `None` for records
+    /// written before it was recorded
+    #[serde(default)]
+    pub format: Option<OutlineFormat>,
+    /// unix timestamp (seconds)
+    pub installed_at: u64,
+    pub scope: Scope,
+    /// font files written, relative to the font dir
+    pub files: Vec<PathBuf>,
+    /// licenses and readmes, relative to the docs dir
+    #[serde(default)]
+    pub docs: Vec<PathBuf>,
+}
+
+impl InstalledFont {
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    }
+
+    /// installed with `--from`, even when the archive sat in a `<tag>/` dir
+    pub fn is_local(&self) -> bool {
+        self.url.starts_with("file://")
+    }
+}
+
+/// per scope record of every font installed by getnf
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(skip)]
+    path: PathBuf,
+    pub fonts: BTreeMap<String, InstalledFont>,
+}
+
+impl Manifest {
+    pub fn load(global: bool) -> Result<Self> {
+        let path = paths::data_dir(global)?.join(MANIFEST_FILE);
+        let mut manifest = match fs::read_to_string(&path) {
+            Ok(s) => {
+                serde_json::from_str::<Manifest>(&s).map_err(|source| GetnfError::Manifest {
+                    path: path.clone(),
+                    source,
+                })?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Manifest::default(),
+            Err(e) => return Err(GetnfError::io(path, e)),
+        };
+        manifest.path = path;
+        Ok(manifest)
+    }
+
+    /// write the manifest through a temporary file so it is never left half written
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| GetnfError::io(dir, e))?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|source| GetnfError::Manifest {
+            path: self.path.clone(),
+            source,
+        })?;
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| GetnfError::io(&tmp, e))?;
+        fs::rename(&tmp, &self.path).map_err(|e| GetnfError::io(&self.path, e))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.fonts.keys().cloned().collect()
+    }
+
+    pub fn get(&self, font: &str) -> Option<&InstalledFont> {
+        self.fonts.get(font)
+    }
+
+    pub fn insert(&mut self, font: String, record: InstalledFont) {
+        self.fonts.insert(font, record);
+    }
+
+    pub fn remove(&mut self, font: &str) -> Option<InstalledFont> {
+        self.fonts.remove(font)
+    }
+}
+
+/// remove `dir` and its sub directories as long as they are empty
+pub fn remove_empty_dirs(dir: &Path) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(GetnfError::io(dir, e)),
+    };
+    for entry in entries {
+        let path = entry.map_err(|e| GetnfError::io(dir, e))?.path();
+        if path.is_dir() {
+            remove_empty_dirs(&path)?;
+        }
+    }
+    match fs::remove_dir(dir) {
+        Ok(()) => Ok(()),
+        // still holds files getnf does not own
+        Err(_) if fs::read_dir(dir).is_ok_and(|mut d| d.next().is_some()) => Ok(()),
+        Err(e) => Err(GetnfError::io(dir, e)),
+    }
+}
@@ -0,0 +1,141 @@
+use std::{env, path::PathBuf};
+
+use crate::error::{GetnfError, Result};
+
+fn env_var(key: &'static str) -> Result<String> {
+    env::var(key).map_err(|_| GetnfError::MissingEnv(key))
+}
+
+fn home() -> Result<PathBuf> {
+    env_var("HOME").map(PathBuf::from)
+}
+
+/// `$XDG_DATA_HOME`, falling back to `~/.local/share`
+fn xdg_data_home() -> Result<PathBuf> {
+    match env::var("XDG_DATA_HOME") {
+        Ok(dir) => Ok(dir.into()),
+        Err(_) => Ok(home()?.join(".local/share")),
+    }
+}
+
+/// `$XDG_CACHE_HOME`, falling back to `~/.cache`
+fn xdg_cache_home() -> Result<PathBuf> {
+    match env::var("XDG_CACHE_HOME") {
+        Ok(dir) => Ok(dir.into()),
+        Err(_) => Ok(home()?.join(".cache")),
+    }
+}
+
+/// `$XDG_CONFIG_HOME`, falling back to `~/.config`
+fn xdg_config_home() -> Result<PathBuf> {
+    match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) => Ok(dir.into()),
+        Err(_) => Ok(home()?.join(".config")),
+    }
+}
+
+/// font dir
+pub fn font_dir(global: bool) -> Result<PathBuf> {
+    let dir = match env::consts::OS {
+        "linux" => {
+            if global {
+                "/usr/local/share/fonts/".into()
+            } else {
+                xdg_data_home()?.join("fonts")
+            }
+        }
+        "macos" => {
+            if global {
+                "/Library/Fonts".into()
+            } else {
+                home()?.join("Library/Fonts")
+            }
+        }
+        "windows" => {
+            if global {
+                let windir = env::var("WINDIR").unwrap_or_else(|_| "C:\\Windows".to_string());
+                PathBuf::from(windir).join("Fonts")
+            } else {
+                PathBuf::from(env_var("LOCALAPPDATA")?)
+                    .join("Microsoft")
+                    .join("Windows")
+                    .join("Fonts")
+            }
+        }
+        os => return Err(GetnfError::UnsupportedPlatform(os)),
+    };
+    Ok(dir)
+}
+
+/// getnf's own state (manifest, ...), kept apart from the fonts
+pub fn data_dir(global: bool) -> Result<PathBuf> {
+    let dir = match env::consts::OS {
+        "linux" => {
+            if global {
+                "/usr/local/share/getnf".into()
+            } else {
+                xdg_data_home()?.join("getnf")
+            }
+        }
+        "macos" => {
+            if global {
+                "/Library/Application Support/getnf".into()
+            } else {
+                home()?.join("Library/Application Support/getnf")
+            }
+        }
+        "windows" => {
+            if global {
+                let program_data =
+                    env::var("PROGRAMDATA").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+                PathBuf::from(program_data).join("getnf")
+            } else {
+                PathBuf::from(env_var("LOCALAPPDATA")?).join("getnf")
+            }
+        }
+        os => return Err(GetnfError::UnsupportedPlatform(os)),
+    };
+    Ok(dir)
+}
+
+/// licenses and readmes shipped with the fonts, as `<font>/<file>`
+pub fn docs_dir(global: bool) -> Result<PathBuf> {
+    Ok(data_dir(global)?.join("docs"))
+}
+
+/// downloaded archives, shared by both scopes
+pub fn cache_dir() -> Result<PathBuf> {
+    let dir = match env::consts::OS {
+        "linux" => xdg_cache_home()?.join("getnf"),
+        "macos" => home()?.join("Library/Caches/getnf"),
+        "windows" => PathBuf::from(env_var("LOCALAPPDATA")?)
+            .join("getnf")
+            .join("cache"),
+        os => return Err(GetnfError::UnsupportedPlatform(os)),
+    };
+    Ok(dir)
+}
+
+/// user configuration
+pub fn config_dir() -> Result<PathBuf> {
+    let dir = match env::consts::OS {
+        "linux" | "macos" => xdg_config_home()?.join("getnf"),
+        "windows" => PathBuf::from(env_var("APPDATA")?).join("getnf"),
+        os => return Err(GetnfError::UnsupportedPlatform(os)),
+    };
+    Ok(dir)
+}
+
+/// system-wide configuration, layered under the user one
+pub fn system_config_dir() -> Result<PathBuf> {
+    let dir = match env::consts::OS {
+        "linux" | "macos" => "/etc/getnf".into(),
+        "windows" => {
+            let program_data =
+                env::var("PROGRAMDATA").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+            PathBuf::from(program_data).join("getnf")
+        }
+        os => return Err(GetnfError::UnsupportedPlatform(os)),
+    };
+    Ok(dir)
+}
@@ -0,0 +1,80 @@
+use std::{
+    io::{self, IsTerminal},
+    time::Duration,
+};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// progress bars on a terminal, plain log lines otherwise
+pub struct Progress {
+    multi: MultiProgress,
+    tty: bool,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        let tty = io::stdout().is_terminal();
+        let multi = if tty {
+            MultiProgress::new()
+        } else {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        };
+        Self { multi, tty }
+    }
+
+    /// bar tracking the download of one font archive
+    pub fn download(&self, font: &str, len: Option<u64>) -> ProgressBar {
+        if !self.tty {
+            println!("downloading {font}");
+            return ProgressBar::hidden();
+        }
+        let bar = match len {
+            Some(len) => ProgressBar::new(len).with_style(
+                ProgressStyle::with_template(
+                    "{prefix:>20.bold} [{bar:30.cyan/blue}] {bytes}/{total_bytes} {binary_bytes_per_sec} eta {eta}",
+                )
+                .unwrap()
+                .progress_chars("=> "),
+            ),
+            None => ProgressBar::no_length().with_style(
+                ProgressStyle::with_template(
+                    "{prefix:>20.bold} {spinner} {bytes} {binary_bytes_per_sec}",
+                )
+                .unwrap(),
+            ),
+        };
+        self.multi.add(bar.with_prefix(font.to_string()))
+    }
+
+    /// spinner shown while an archive is unpacked
+    pub fn extract(&self, font: &str) -> ProgressBar {
+        if !self.tty {
+            println!("extracting {font}");
+            return ProgressBar::hidden();
+        }
+        let spinner = ProgressBar::new_spinner()
+            .with_style(ProgressStyle::with_template("{prefix:>20.bold} {spinner} {msg}").unwrap())
+            .with_prefix(font.to_string())
+            .with_message("extracting");
+        let spinner = self.multi.add(spinner);
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        spinner
+    }
+
+    /// a warning on stderr that does not tear the bars
+    pub fn warn(&self, msg: impl AsRef<str>) {
+        if self.tty {
+            self.multi.suspend(|| eprintln!("{}", msg.as_ref()));
+        } else {
+            eprintln!("{}", msg.as_ref());
+        }
+    }
+
+    pub fn println(&self, msg: impl AsRef<str>) {
+        if self.tty {
+            self.multi.println(msg).ok();
+        } else {
+            println!("{}", msg.as_ref());
+        }
+    }
+}
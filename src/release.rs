@@ -0,0 +1,150 @@
+use std::cmp::Ordering;
+
+use reqwest::StatusCode;
+use semver::Version;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+
+use crate::{
+    endpoints::Endpoints,
+    error::{GetnfError, Result},
+    http,
+};
+
+/// releases fetched per page, the most the GitHub API allows
+const PER_PAGE: usize = 100;
+
+/// a GitHub release of the Nerd Fonts repository
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    #[serde(rename = "tag_name")]
+    pub tag: String,
+    /// RFC 3339, unset for drafts
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    /// release notes, in markdown
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub size: u64,
+}
+
+impl Release {
+    /// day the release was published, `YYYY-MM-DD`
+    pub fn date(&self) -> &str {
+        self.published_at
+            .as_deref()
+            .and_then(|at| at.get(..10))
+            .unwrap_or("-")
+    }
+
+    pub fn size(&self) -> u64 {
+        self.assets.iter().map(|a| a.size).sum()
+    }
+}
+
+/// every release, newest first, following the API's pagination
+pub fn list(endpoints: &Endpoints) -> Result<Vec<Release>> {
+    let mut releases = vec![];
+    for page in 1.. {
+        let url = endpoints.api(&format!("/releases?per_page={PER_PAGE}&page={page}"));
+        let batch: Vec<Release> = parse(&url, http::request(&url)?)?;
+        let last = batch.len() < PER_PAGE;
+        releases.extend(batch);
+        if last {
+            break;
+        }
+    }
+    Ok(releases)
+}
+
+/// tag of the latest release
+pub fn latest(endpoints: &Endpoints) -> Result<String> {
+    let url = endpoints.api("/releases/latest");
+    let body = http::request(&url)?;
+    body["tag_name"]
+        .as_str()
+        .map(Into::into)
+        .ok_or_else(|| GetnfError::InvalidResponse {
+            url,
+            reason: "missing tag_name".into(),
+        })
+}
+
+/// the release tagged `tag`
+pub fn fetch(endpoints: &Endpoints, tag: &str) -> Result<Release> {
+    let url = endpoints.api(&format!("/releases/tags/{tag}"));
+    match http::request(&url) {
+        Ok(body) => parse(&url, body),
+        Err(GetnfError::HttpStatus { status, .. }) if status == StatusCode::NOT_FOUND => {
+            Err(GetnfError::ReleaseNotFound(tag.into()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn parse<T: DeserializeOwned>(url: &str, body: Value) -> Result<T> {
+    serde_json::from_value(body).map_err(|e| GetnfError::InvalidResponse {
+        url: url.into(),
+        reason: e.to_string(),
+    })
+}
+
+/// parse a release tag such as `v3.2.1`
+pub fn parse_tag(tag: &str) -> Option<Version> {
+    let tag = tag.trim();
+    let tag = tag.strip_prefix(['v', 'V']).unwrap_or(tag);
+    Version::parse(tag).ok()
+}
+
+/// order two release tags, semver aware, falling back to plain string order
+/// when one of them is not a version
+pub fn compare_tags(a: &str, b: &str) -> Ordering {
+    match (parse_tag(a), parse_tag(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// `3.1.1` and `V3.1.1` as the `v3.1.1` Nerd Fonts tags use
+pub fn normalize_tag(tag: &str) -> String {
+    match parse_tag(tag) {
+        Some(version) => format!("v{version}"),
+        None => tag.trim().into(),
+    }
+}
+
+/// whether `installed` is older than `latest`
+pub fn is_outdated(installed: &str, latest: &str) -> bool {
+    compare_tags(installed, latest) == Ordering::Less
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_tags_as_versions() {
+        assert_eq!(compare_tags("v3.10.0", "v3.9.0"), Ordering::Greater);
+        assert_eq!(compare_tags("v3.2.1", "3.2.1"), Ordering::Equal);
+        assert_eq!(compare_tags("V2.3.3", "v3.0.0"), Ordering::Less);
+        assert!(is_outdated("v3.9.0", "v3.10.0"));
+        assert!(!is_outdated("v3.10.0", "v3.9.0"));
+        assert!(!is_outdated("v3.2.1", "v3.2.1"));
+    }
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(normalize_tag(" 3.1.1 "), "v3.1.1");
+        assert_eq!(normalize_tag("V3.1.1"), "v3.1.1");
+        assert_eq!(normalize_tag("latest"), "latest");
+        assert!(parse_tag("local").is_none());
+    }
+}